@@ -0,0 +1,370 @@
+// #[cfg(not(target_os="linux"))]
+#[cfg(target_os="linux")]
+
+mod device {
+
+    use alsa::{Round, card::Card, ctl::Ctl, mixer::{MilliBel, Mixer, Selem, SelemChannelId}};
+    use crate::{DeviceType, curve::VolumeCurve, debug_eprintln, device::{self, ChannelVolume, DeviceInfo, DeviceTrait}, error::Error};
+
+    // Mixer elements tried in order, cards without a `Master` usually expose one of the others
+    const PLAYBACK_ELEMENTS: [&str; 5] = ["Master", "PCM", "Speaker", "Headphone", "Digital"];
+    const CAPTURE_ELEMENTS: [&str; 4] = ["Capture", "Mic", "Internal Mic", "Digital"];
+
+    // Elements reporting a wider range use a mute step as their minimum (e.g. -99999.99 dB)
+    const MAX_DB_RANGE: f32 = 150.0;
+
+    /// Sound card controlled through its ALSA mixer, used when no sound server is running
+    pub struct AlsaDevice {
+        card: i32,
+        dev_type: DeviceType,
+    }
+
+    impl DeviceTrait for AlsaDevice {
+
+        // Name is the card name (e.g. "HDA Intel PCH")
+        fn from_name(name: String) -> Result<Self, Error> {
+            crate::alsa::get_cards(DeviceType::Output)?.into_iter()
+                .chain(crate::alsa::get_cards(DeviceType::Input)?)
+                .find(|device| device.get_name().is_ok_and(|device_name| device_name == name))
+                .ok_or(Error::DeviceNotFound)
+        }
+
+        // UID is the control name of the card, capture gets a suffix (e.g. "hw:0" and "hw:0,capture")
+        fn from_uid(uid: String) -> Result<Self, Error> {
+            let (card, dev_type) = parse_uid(&uid).ok_or(Error::DeviceNotFound)?;
+            let device = AlsaDevice::new(card, dev_type);
+            if device.has_element() {
+                return Ok(device);
+            }
+            Err(Error::DeviceNotFound)
+        }
+
+        fn get_name(&self) -> Result<String, Error> {
+            Card::new(self.card).get_name()
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to read card name {}", e)))
+        }
+
+        fn get_uid(&self) -> Result<String, Error> {
+            Ok(to_uid(self.card, self.dev_type))
+        }
+
+        fn get_device_type(&self) -> Result<DeviceType, Error> {
+            Ok(self.dev_type)
+        }
+
+        fn get_vol(&self) -> Result<f32, Error> {
+            if self.get_mute()? {
+                return Ok(0.0);
+            }
+            let volumes = self.get_channel_vols()?;
+            Ok(volumes.iter().map(|channel| channel.volume).fold(0.0, f32::max))
+        }
+
+        fn set_vol(&self, value: f32) -> Result<(), Error> {
+            device::check_vol(&self.get_uid()?, value)?;
+            let dev_type = self.dev_type;
+            self.with_selem(|selem| {
+                let raw = from_percent(get_range(selem, dev_type), value);
+                let result = match dev_type {
+                    DeviceType::Input => selem.set_capture_volume_all(raw),
+                    _ => selem.set_playback_volume_all(raw),
+                };
+                result.map_err(|e| Error::VolumeSetFailed(format!("Failed to adjust mixer volume {}", e)))
+            })
+        }
+
+        // Elements without a switch cannot be muted and always report unmuted
+        fn get_mute(&self) -> Result<bool, Error> {
+            let dev_type = self.dev_type;
+            self.with_selem(|selem| {
+                let switch = match dev_type {
+                    DeviceType::Input if selem.has_capture_switch() => selem.get_capture_switch(SelemChannelId::mono()),
+                    DeviceType::Input => return Ok(false),
+                    _ if selem.has_playback_switch() => selem.get_playback_switch(SelemChannelId::mono()),
+                    _ => return Ok(false),
+                };
+                switch.map(|value| value == 0)
+                    .map_err(|e| Error::DeviceAccessFailed(format!("Failed to read mixer switch {}", e)))
+            })
+        }
+
+        fn set_mute(&self, mute: bool) -> Result<(), Error> {
+            let dev_type = self.dev_type;
+            let value = if mute { 0 } else { 1 };
+            self.with_selem(|selem| {
+                let result = match dev_type {
+                    DeviceType::Input if selem.has_capture_switch() => selem.set_capture_switch_all(value),
+                    _ if dev_type != DeviceType::Input && selem.has_playback_switch() => selem.set_playback_switch_all(value),
+                    _ => {
+                        return Err(Error::MuteSetFailed(format!("Mixer element {} has no mute switch", get_element_name(selem))));
+                    }
+                };
+                result.map_err(|e| Error::MuteSetFailed(format!("Failed to adjust mixer switch {}", e)))
+            })
+        }
+
+        fn get_channel_vols(&self) -> Result<Vec<ChannelVolume>, Error> {
+            let dev_type = self.dev_type;
+            self.with_selem(|selem| {
+                let range = get_range(selem, dev_type);
+                get_channels(selem, dev_type).into_iter()
+                    .map(|channel| {
+                        let raw = match dev_type {
+                            DeviceType::Input => selem.get_capture_volume(channel),
+                            _ => selem.get_playback_volume(channel),
+                        };
+                        let raw = raw.map_err(|e| Error::VolumeCaptureFailed(format!("Failed to read mixer volume {}", e)))?;
+                        Ok(ChannelVolume {
+                            position: Selem::channel_name(channel).unwrap_or("Unknown").to_string(),
+                            volume: to_percent(range, raw),
+                        })
+                    })
+                    .collect()
+            })
+        }
+
+        fn set_channel_vol(&self, channel: usize, value: f32) -> Result<(), Error> {
+            device::check_vol(&self.get_uid()?, value)?;
+            let dev_type = self.dev_type;
+            self.with_selem(|selem| {
+                let channels = get_channels(selem, dev_type);
+                let id = *channels.get(channel)
+                    .ok_or(Error::VolumeSetFailed(format!("Channel {} out of range, device has {} channels", channel, channels.len())))?;
+                let raw = from_percent(get_range(selem, dev_type), value);
+                let result = match dev_type {
+                    DeviceType::Input => selem.set_capture_volume(id, raw),
+                    _ => selem.set_playback_volume(id, raw),
+                };
+                result.map_err(|e| Error::VolumeSetFailed(format!("Failed to adjust mixer volume {}", e)))
+            })
+        }
+
+        fn get_vol_db(&self) -> Result<f32, Error> {
+            if self.get_mute()? {
+                return Ok(f32::NEG_INFINITY);
+            }
+            let dev_type = self.dev_type;
+            self.with_selem(|selem| Ok(get_channel_dbs(selem, dev_type)?.into_iter().map(|(_, db)| db).fold(f32::NEG_INFINITY, f32::max)))
+        }
+
+        // Channels keep their offset to the loudest one, the driver rounds to its nearest step
+        fn set_vol_db(&self, db: f32) -> Result<(), Error> {
+            if db.is_nan() {
+                return Err(Error::VolumeSetFailed(format!("Volume {} dB is not a number", db)));
+            }
+            let dev_type = self.dev_type;
+            self.with_selem(|selem| {
+                let channels = get_channel_dbs(selem, dev_type)?;
+                let max = channels.iter().map(|(_, db)| *db).fold(f32::NEG_INFINITY, f32::max);
+                for (channel, channel_db) in channels {
+                    let value = MilliBel::from_db(if max.is_finite() { channel_db - max + db } else { db });
+                    let result = match dev_type {
+                        DeviceType::Input => selem.set_capture_db(channel, value, Round::Floor),
+                        _ => selem.set_playback_db(channel, value, Round::Floor),
+                    };
+                    result.map_err(|e| Error::VolumeSetFailed(format!("Failed to adjust mixer volume {}", e)))?;
+                }
+                Ok(())
+            })
+        }
+
+        // Mixer dB come from the driver's TLV data, elements without it have no dB scale at all
+        fn has_db_volume(&self) -> Result<bool, Error> {
+            let dev_type = self.dev_type;
+            self.with_selem(|selem| Ok(get_channel_dbs(selem, dev_type).is_ok()))
+        }
+
+        // Mixer elements are hardware controls, the driver comes from the card's control interface
+        fn get_info(&self) -> Result<DeviceInfo, Error> {
+            let dev_type = self.dev_type;
+            let channel_map = self.get_channel_vols()?.into_iter().map(|channel| channel.position).collect::<Vec<_>>();
+            let (volume_steps, db_volume) = self.with_selem(|selem| {
+                let (min, max) = get_range(selem, dev_type);
+                Ok(((max - min + 1).max(0) as u32, get_channel_dbs(selem, dev_type).is_ok()))
+            })?;
+            let driver = Ctl::new(&self.get_ctl_name(), false).ok()
+                .and_then(|ctl| ctl.card_info().ok()?.get_driver().ok().map(|driver| driver.to_string()));
+            Ok(DeviceInfo {
+                driver,
+                channels: Some(channel_map.len() as u8),
+                channel_map: Some(channel_map),
+                card: u32::try_from(self.card).ok(),
+                hw_volume: Some(true),
+                db_volume: Some(db_volume),
+                volume_steps: Some(volume_steps),
+                ..DeviceInfo::default()
+            })
+        }
+
+        // Raw steps of elements with a dB scale are close to even dB steps, the others have no known curve
+        fn get_native_curve(&self) -> Result<VolumeCurve, Error> {
+            let dev_type = self.dev_type;
+            self.with_selem(|selem| {
+                let (min, max) = match dev_type {
+                    DeviceType::Input => selem.get_capture_db_range(),
+                    _ => selem.get_playback_db_range(),
+                };
+                let range = max.to_db() - min.to_db();
+                if range > 0.0 && range <= MAX_DB_RANGE {
+                    Ok(VolumeCurve::Logarithmic { range })
+                } else {
+                    Ok(VolumeCurve::Linear)
+                }
+            })
+        }
+    }
+
+    impl AlsaDevice {
+        pub(crate) fn new(card: i32, dev_type: DeviceType) -> Self {
+            AlsaDevice {
+                card,
+                dev_type,
+            }
+        }
+
+        pub fn get_card_index(&self) -> i32 {
+            self.card
+        }
+
+        pub fn get_ctl_name(&self) -> String {
+            format!("hw:{}", self.card)
+        }
+
+        /// Name of the mixer element the device controls (e.g. "Master")
+        pub fn get_element_name(&self) -> Result<String, Error> {
+            self.with_selem(|selem| Ok(get_element_name(selem)))
+        }
+
+        pub(crate) fn has_element(&self) -> bool {
+            self.with_selem(|_| Ok(())).is_ok()
+        }
+
+        fn with_selem<R>(&self, action: impl FnOnce(&Selem) -> Result<R, Error>) -> Result<R, Error> {
+            let mixer = Mixer::new(&self.get_ctl_name(), false)
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to open mixer {} {}", self.get_ctl_name(), e)))?;
+            let dev_type = self.dev_type;
+            let preferred: &[&str] = match dev_type {
+                DeviceType::Input => &CAPTURE_ELEMENTS,
+                _ => &PLAYBACK_ELEMENTS,
+            };
+
+            let selems = mixer.iter()
+                .filter_map(Selem::new)
+                .filter(|selem| match dev_type {
+                    DeviceType::Input => selem.has_capture_volume(),
+                    _ => selem.has_playback_volume(),
+                })
+                .collect::<Vec<_>>();
+            let selem = preferred.iter()
+                .find_map(|name| selems.iter().find(|selem| get_element_name(selem) == *name))
+                .or(selems.first());
+            match selem {
+                Some(selem) => action(selem),
+                None => {
+                    debug_eprintln(&format!("No mixer element with volume on {}", self.get_ctl_name()));
+                    Err(Error::DeviceNotFound)
+                }
+            }
+        }
+    }
+
+    // Playback and capture of a card share its control, the suffix keeps their uids apart
+    fn to_uid(card: i32, dev_type: DeviceType) -> String {
+        match dev_type {
+            DeviceType::Input => format!("hw:{},capture", card),
+            _ => format!("hw:{}", card),
+        }
+    }
+
+    fn parse_uid(uid: &str) -> Option<(i32, DeviceType)> {
+        let card = uid.strip_prefix("hw:")?;
+        let (card, dev_type) = match card.strip_suffix(",capture") {
+            Some(card) => (card, DeviceType::Input),
+            None => (card, DeviceType::Output),
+        };
+        Some((card.parse::<i32>().ok()?, dev_type))
+    }
+
+    fn get_element_name(selem: &Selem) -> String {
+        selem.get_id().get_name().unwrap_or_default().to_string()
+    }
+
+    fn get_range(selem: &Selem, dev_type: DeviceType) -> (i64, i64) {
+        match dev_type {
+            DeviceType::Input => selem.get_capture_volume_range(),
+            _ => selem.get_playback_volume_range(),
+        }
+    }
+
+    fn get_channels(selem: &Selem, dev_type: DeviceType) -> Vec<SelemChannelId> {
+        SelemChannelId::all().iter()
+            .copied()
+            .filter(|channel| match dev_type {
+                DeviceType::Input => selem.has_capture_channel(*channel),
+                _ => selem.has_playback_channel(*channel),
+            })
+            .collect()
+    }
+
+    fn get_channel_dbs(selem: &Selem, dev_type: DeviceType) -> Result<Vec<(SelemChannelId, f32)>, Error> {
+        get_channels(selem, dev_type).into_iter()
+            .map(|channel| {
+                let db = match dev_type {
+                    DeviceType::Input => selem.get_capture_vol_db(channel),
+                    _ => selem.get_playback_vol_db(channel),
+                };
+                db.map(|db| (channel, db.to_db()))
+                    .map_err(|e| Error::VolumeCaptureFailed(format!("Mixer element {} has no dB scale {}", get_element_name(selem), e)))
+            })
+            .collect()
+    }
+
+    // Raw mixer values are linear steps between the element's min and max
+    fn to_percent((min, max): (i64, i64), raw: i64) -> f32 {
+        if max <= min {
+            return 0.0;
+        }
+        (raw - min) as f32 / (max - min) as f32
+    }
+
+    fn from_percent((min, max): (i64, i64), value: f32) -> i64 {
+        min + ((max - min) as f32 * value).round() as i64
+    }
+
+    #[cfg(test)]
+    mod test {
+
+        use crate::DeviceType;
+        use super::{parse_uid, to_uid};
+
+        #[test]
+        fn test_alsa_uid() {
+            // A card with both playback and capture gives each direction its own uid
+            let output = to_uid(0, DeviceType::Output);
+            let input = to_uid(0, DeviceType::Input);
+            assert_eq!(output, "hw:0");
+            assert_eq!(input, "hw:0,capture");
+            assert_eq!(parse_uid(&output), Some((0, DeviceType::Output)));
+            assert_eq!(parse_uid(&input), Some((0, DeviceType::Input)));
+            assert_eq!(parse_uid("hw:12,capture"), Some((12, DeviceType::Input)));
+            assert_eq!(parse_uid("hw:x"), None);
+            assert_eq!(parse_uid("default"), None);
+        }
+    }
+
+}
+
+#[cfg(not(target_os="linux"))]
+// #[cfg(target_os="linux")]
+
+mod device {
+
+    use crate::device::DeviceTrait;
+
+    pub struct AlsaDevice {}
+
+    impl DeviceTrait for AlsaDevice {}
+
+}
+
+pub(crate) use device::*;
@@ -0,0 +1,120 @@
+pub mod device;
+
+// #[cfg(not(target_os="linux"))]
+#[cfg(target_os="linux")]
+// Talks to the kernel mixer of each card directly, no sound server required
+pub mod alsa {
+    use alsa::card::Iter;
+    use crate::{DeviceType, alsa::device::AlsaDevice, debug_eprintln, device::DeviceTrait, error::Error};
+
+    /// Gathers every card with a mixer element for `device_type`
+    pub fn get_cards(device_type: DeviceType) -> Result<Vec<AlsaDevice>, Error> {
+        let mut devices = Vec::new();
+        for card in Iter::new() {
+            match card {
+                Ok(card) => {
+                    let device = AlsaDevice::new(card.get_index(), device_type);
+                    if device.has_element() {
+                        devices.push(device);
+                    }
+                },
+                Err(e) => {
+                    debug_eprintln(&format!("Failed to read sound card {}", e));
+                    return Err(Error::DeviceEnumerationFailed(format!("Failed to read sound card {}", e)));
+                }
+            }
+        }
+        Ok(devices)
+    }
+
+    pub fn get_device_identifiers() -> Result<Vec<(String, String)>, Error> {
+        get_cards(DeviceType::Output)?.into_iter()
+            .map(|device| Ok((device.get_uid()?, device.get_name()?)))
+            .collect()
+    }
+
+    // ALSA's default device is the first card unless asound.conf says otherwise
+    pub fn get_default_output_dev() -> Result<AlsaDevice, Error> {
+        get_cards(DeviceType::Output)?.into_iter().next().ok_or(Error::DeviceNotFound)
+    }
+
+    pub fn get_default_input_dev() -> Result<AlsaDevice, Error> {
+        get_cards(DeviceType::Input)?.into_iter().next().ok_or(Error::DeviceNotFound)
+    }
+
+    // Volume Controls
+    pub fn get_sound_devices() -> Result<Vec<String>, Error> {
+        Ok(get_device_identifiers()?.into_iter().map(|(_id, name)| name).collect())
+    }
+
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Ok(get_device_identifiers()?.into_iter().map(|(id, _name)| id).collect())
+    }
+
+    pub fn get_vol() -> Result<f32, Error> {
+        get_default_output_dev()?.get_vol()
+    }
+
+    pub fn set_vol(value: f32) -> Result<(), Error> {
+        get_default_output_dev()?.set_vol(value)
+    }
+
+    pub fn get_mute() -> Result<bool, Error> {
+        get_default_output_dev()?.get_mute()
+    }
+
+    pub fn set_mute(state: bool) -> Result<(), Error> {
+        get_default_output_dev()?.set_mute(state)
+    }
+
+    // Input Controls
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        get_cards(DeviceType::Input)?.iter().map(|device| device.get_name()).collect()
+    }
+
+    pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+        get_cards(DeviceType::Input)?.iter().map(|device| device.get_uid()).collect()
+    }
+
+    pub fn get_input_vol() -> Result<f32, Error> {
+        get_default_input_dev()?.get_vol()
+    }
+
+    pub fn set_input_vol(value: f32) -> Result<(), Error> {
+        get_default_input_dev()?.set_vol(value)
+    }
+
+    pub fn get_input_mute() -> Result<bool, Error> {
+        get_default_input_dev()?.get_mute()
+    }
+
+    pub fn set_input_mute(state: bool) -> Result<(), Error> {
+        get_default_input_dev()?.set_mute(state)
+    }
+
+}
+
+#[cfg(not(target_os="linux"))]
+// #[cfg(target_os="linux")]
+pub mod alsa {
+    use crate::error::Error;
+
+    pub fn get_sound_devices() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+}
+
+pub(crate) use self::alsa::*;
@@ -0,0 +1,248 @@
+use std::{future::Future, pin::Pin, sync::{Mutex, mpsc::{self, Sender}}, task::{Context, Poll}, thread, time::Duration};
+use futures_channel::{mpsc::UnboundedReceiver, oneshot};
+use futures_core::Stream;
+use crate::{DeviceType, SystemState, curve::VolumeCurve, device::{ChannelVolume, Device, DeviceDescriptor, DeviceInfo, Port}, error::Error, event::{Event, Subscription}, fade::Fade, snapshot::{RestoreReport, Snapshot}};
+
+// `async` versions of the crate functions and devices, enabled with the `async` feature
+//
+// Every blocking call runs on a worker thread owned by cpvc and the futures only wait for its result,
+// so no executor thread ever drives a sound server connection
+
+type Job = Box<dyn FnOnce() + Send>;
+type DeviceJob = Box<dyn FnOnce(&mut Device) + Send>;
+
+// Worker of the crate functions, started on first use
+static WORKER: Mutex<Option<Sender<Job>>> = Mutex::new(None);
+
+// Mock systems are per thread, jobs run against the system of the thread that created them
+fn run<R: Send + 'static>(job: impl FnOnce() -> Result<R, Error> + Send + 'static) -> impl Future<Output = Result<R, Error>> {
+    let (sender, receiver) = oneshot::channel();
+    #[cfg(feature = "mock")]
+    let system = crate::mock::MockSystem::current();
+    submit(Box::new(move || {
+        #[cfg(feature = "mock")]
+        system.install();
+        let _ = sender.send(job());
+    }));
+    async move {
+        receiver.await.unwrap_or(Err(Error::External(format!("cpvc worker stopped without a result"))))
+    }
+}
+
+fn submit(mut job: Job) {
+    let mut worker = WORKER.lock().unwrap();
+    if let Some(sender) = worker.as_ref() {
+        match sender.send(job) {
+            Ok(()) => return,
+            // A panicking job took the previous worker down, a new one takes over
+            Err(mpsc::SendError(returned)) => job = returned,
+        }
+    }
+    let (sender, receiver) = mpsc::channel::<Job>();
+    thread::spawn(move || {
+        for job in receiver {
+            job();
+        }
+    });
+    let _ = sender.send(job);
+    worker.replace(sender);
+}
+
+pub async fn get_sound_devices() -> Result<Vec<String>, Error> {
+    run(crate::try_get_sound_devices).await
+}
+
+pub async fn get_system_volume() -> Result<f32, Error> {
+    run(crate::try_get_system_volume).await
+}
+
+pub async fn set_system_volume(percent: f32) -> Result<(), Error> {
+    run(move || crate::try_set_system_volume(percent).map(|_| ())).await
+}
+
+pub async fn get_mute() -> Result<bool, Error> {
+    run(crate::try_get_mute).await
+}
+
+pub async fn set_mute(mute: bool) -> Result<(), Error> {
+    run(move || crate::try_set_mute(mute).map(|_| ())).await
+}
+
+pub async fn get_input_devices() -> Result<Vec<String>, Error> {
+    run(crate::try_get_input_devices).await
+}
+
+pub async fn get_input_volume() -> Result<f32, Error> {
+    run(crate::try_get_input_volume).await
+}
+
+pub async fn set_input_volume(percent: f32) -> Result<(), Error> {
+    run(move || crate::try_set_input_volume(percent).map(|_| ())).await
+}
+
+pub async fn get_input_mute() -> Result<bool, Error> {
+    run(crate::try_get_input_mute).await
+}
+
+pub async fn set_input_mute(mute: bool) -> Result<(), Error> {
+    run(move || crate::try_set_input_mute(mute).map(|_| ())).await
+}
+
+pub async fn get_default_output_device() -> Result<AsyncDevice, Error> {
+    AsyncDevice::open(crate::get_default_output_device).await
+}
+
+pub async fn get_default_input_device() -> Result<AsyncDevice, Error> {
+    AsyncDevice::open(crate::get_default_input_device).await
+}
+
+pub async fn fade_system_volume(target: f32, duration: Duration, curve: VolumeCurve) -> Result<Fade, Error> {
+    run(move || crate::fade_system_volume(target, duration, curve)).await
+}
+
+pub async fn system_state() -> Result<SystemState, Error> {
+    run(crate::system_state).await
+}
+
+pub async fn snapshot() -> Result<Snapshot, Error> {
+    run(crate::snapshot).await
+}
+
+pub async fn restore(snapshot: Snapshot) -> Result<RestoreReport, Error> {
+    run(move || crate::restore(&snapshot)).await
+}
+
+/// Stream of change notifications, see `crate::subscribe`
+pub async fn subscribe() -> Result<EventStream, Error> {
+    let (sender, receiver) = futures_channel::mpsc::unbounded();
+    let subscription = run(move || crate::subscribe(move |event| {
+        let _ = sender.unbounded_send(event);
+    })).await?;
+    Ok(EventStream {
+        receiver,
+        subscription,
+    })
+}
+
+/// `futures::Stream` of events, events stop once the stream is dropped or `unsubscribe` is called
+pub struct EventStream {
+    receiver: UnboundedReceiver<Event>,
+    subscription: Subscription,
+}
+
+impl EventStream {
+    pub fn unsubscribe(self) {
+        self.subscription.unsubscribe();
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+/// Device living on its own worker thread, every method is an `async` version of the `Device` one
+///
+/// The worker stops once the `AsyncDevice` is dropped
+pub struct AsyncDevice {
+    jobs: Sender<DeviceJob>,
+}
+
+// Generates the `async` counterpart of a `Device` method
+macro_rules! forward {
+    ($name:ident($($arg:ident: $arg_type:ty),*) -> $result:ty) => {
+        pub async fn $name(&self, $($arg: $arg_type),*) -> Result<$result, Error> {
+            self.run(move |device| device.$name($($arg),*)).await
+        }
+    };
+}
+
+impl AsyncDevice {
+    pub async fn from_uid(uid: String) -> Result<Self, Error> {
+        AsyncDevice::open(move || Device::from_uid(uid)).await
+    }
+
+    pub async fn from_name(name: String) -> Result<Self, Error> {
+        AsyncDevice::open(move || Device::from_name(name)).await
+    }
+
+    /// Moves `device` to a worker thread, it is opened again there with its curve
+    pub async fn from_device(device: &Device) -> Result<Self, Error> {
+        AsyncDevice::open(device.get_opener()?).await
+    }
+
+    // The device is opened on the worker, some platform handles cannot move between threads
+    async fn open(open: impl FnOnce() -> Result<Device, Error> + Send + 'static) -> Result<Self, Error> {
+        let (jobs, receiver) = mpsc::channel::<DeviceJob>();
+        let (opened, result) = oneshot::channel();
+        #[cfg(feature = "mock")]
+        let system = crate::mock::MockSystem::current();
+        thread::spawn(move || {
+            #[cfg(feature = "mock")]
+            system.install();
+            let mut device = match open() {
+                Ok(device) => device,
+                Err(error) => {
+                    let _ = opened.send(Err(error));
+                    return;
+                }
+            };
+            let _ = opened.send(Ok(()));
+            for job in receiver {
+                job(&mut device);
+            }
+        });
+        result.await.unwrap_or(Err(Error::External(format!("cpvc device worker stopped while opening"))))?;
+        Ok(AsyncDevice {
+            jobs,
+        })
+    }
+
+    fn run<R: Send + 'static>(&self, job: impl FnOnce(&mut Device) -> Result<R, Error> + Send + 'static) -> impl Future<Output = Result<R, Error>> {
+        let (sender, receiver) = oneshot::channel();
+        let sent = self.jobs.send(Box::new(move |device| {
+            let _ = sender.send(job(device));
+        }));
+        async move {
+            sent.map_err(|_| Error::External(format!("cpvc device worker stopped")))?;
+            receiver.await.unwrap_or(Err(Error::External(format!("cpvc device worker stopped without a result"))))
+        }
+    }
+
+    forward!(get_name() -> String);
+    forward!(get_uid() -> String);
+    forward!(get_device_type() -> DeviceType);
+    forward!(get_vol() -> f32);
+    forward!(set_vol(vol: f32) -> ());
+    forward!(get_mute() -> bool);
+    forward!(set_mute(mute: bool) -> ());
+    forward!(make_default(move_streams: bool) -> ());
+    forward!(get_ports() -> Vec<Port>);
+    forward!(get_active_port() -> Option<Port>);
+    forward!(set_active_port(name: String) -> ());
+    forward!(get_channel_vols() -> Vec<ChannelVolume>);
+    forward!(set_channel_vol(channel: usize, vol: f32) -> ());
+    forward!(get_balance() -> f32);
+    forward!(set_balance(balance: f32) -> ());
+    forward!(get_fade() -> f32);
+    forward!(set_fade(fade: f32) -> ());
+    forward!(get_vol_db() -> f32);
+    forward!(set_vol_db(db: f32) -> ());
+    forward!(has_db_volume() -> bool);
+    forward!(supports_boost() -> bool);
+    forward!(set_boost_allowed(allowed: bool) -> ());
+    forward!(is_boost_allowed() -> bool);
+    forward!(set_max_vol(max_vol: f32) -> ());
+    forward!(get_max_vol() -> f32);
+    forward!(get_info() -> DeviceInfo);
+    forward!(get_descriptor() -> DeviceDescriptor);
+    forward!(fade_to(target: f32, duration: Duration, curve: VolumeCurve) -> Fade);
+    forward!(set_curve(curve: Option<VolumeCurve>) -> ());
+
+    pub async fn get_curve(&self) -> Result<VolumeCurve, Error> {
+        self.run(|device| Ok(device.get_curve())).await
+    }
+}
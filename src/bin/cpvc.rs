@@ -0,0 +1,261 @@
+use std::{env, process::ExitCode};
+use cpvc::{DeviceType, device::{Device, DeviceDescriptor}, error::Error, pulseaudio::options::ConnectionOptions};
+
+const USAGE: &str = "Usage: cpvc [--json] [--input] [--device <uid or name>] [--server <address>] <command>
+
+Commands:
+  list            List output and input devices
+  get             Print the volume and mute state
+  set <value>     Set the volume, absolute (0.5, 50%) or relative (+10%, -0.05)
+  mute            Mute the device
+  unmute          Unmute the device
+  toggle          Flip the mute state
+  default         Print the default device, or make --device the default
+
+Options:
+  --json          Print JSON instead of text
+  --input         Target the default input device instead of the default output
+  --device, -d    Target a device by uid or name, takes precedence over --input
+  --server, -s    PulseAudio server, a socket (unix:/path), tcp:host:port or a runtime directory,
+                  PULSE_SERVER and PULSE_COOKIE are used when unset
+
+Exit codes: 0 success, 1 audio error, 2 invalid usage, 3 device not found";
+
+// Exit codes, kept stable for scripts
+const EXIT_FAILURE: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_NOT_FOUND: u8 = 3;
+
+struct Options {
+    json: bool,
+    device_type: DeviceType,
+    device: Option<String>,
+    server: Option<String>,
+    command: String,
+    args: Vec<String>,
+}
+
+enum CliError {
+    Usage(String),
+    Audio(Error),
+}
+
+impl From<Error> for CliError {
+    fn from(error: Error) -> Self {
+        CliError::Audio(error)
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            ExitCode::from(EXIT_USAGE)
+        },
+        Err(CliError::Audio(error)) => {
+            if options.json {
+                eprintln!("{{\"error\":{}}}", json_string(&format!("{:?}", error)));
+            } else {
+                eprintln!("cpvc: {:?}", error);
+            }
+            ExitCode::from(if error == Error::DeviceNotFound { EXIT_NOT_FOUND } else { EXIT_FAILURE })
+        }
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        json: false,
+        device_type: DeviceType::Output,
+        device: None,
+        server: None,
+        command: String::new(),
+        args: Vec::new(),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--input" => options.device_type = DeviceType::Input,
+            "--device" | "-d" => {
+                options.device = Some(args.next().ok_or(format!("{} needs a uid or name", arg))?);
+            },
+            "--server" | "-s" => {
+                options.server = Some(args.next().ok_or(format!("{} needs an address", arg))?);
+            },
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            },
+            // Relative values such as -10% are arguments, not options
+            _ if options.command.is_empty() && arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ if options.command.is_empty() => options.command = arg,
+            _ => options.args.push(arg),
+        }
+    }
+    if options.command.is_empty() {
+        return Err(format!("Missing command"));
+    }
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), CliError> {
+    if let Some(server) = options.server.as_ref() {
+        cpvc::set_connection_options(ConnectionOptions { server: Some(server.clone()), ..ConnectionOptions::default() })?;
+    }
+    match options.command.as_str() {
+        "list" => list(options),
+        "get" => {
+            let device = get_device(options)?;
+            print_device(options, &device)
+        },
+        "set" => {
+            let [value] = options.args.as_slice() else {
+                return Err(CliError::Usage(format!("set needs exactly one value")));
+            };
+            let device = get_device(options)?;
+            let volume = parse_volume(value, &device)?;
+            device.set_vol(volume)?;
+            print_device(options, &device)
+        },
+        "mute" | "unmute" | "toggle" => {
+            let device = get_device(options)?;
+            let mute = match options.command.as_str() {
+                "mute" => true,
+                "unmute" => false,
+                _ => !device.get_mute()?,
+            };
+            device.set_mute(mute)?;
+            print_device(options, &device)
+        },
+        "default" => {
+            let device = get_device(options)?;
+            if options.device.is_some() {
+                match device.get_device_type()? {
+                    DeviceType::Input => cpvc::set_default_input_device(&device)?,
+                    _ => cpvc::set_default_output_device(&device)?,
+                }
+            }
+            print_device(options, &device)
+        },
+        command => Err(CliError::Usage(format!("Unknown command {}", command))),
+    }
+}
+
+// Devices are looked up by uid first, names are only unique on most systems
+fn get_device(options: &Options) -> Result<Device, Error> {
+    match options.device.as_ref() {
+        Some(id) => match Device::from_uid(id.clone()) {
+            Err(Error::DeviceNotFound) => Device::from_name(id.clone()),
+            result => result,
+        },
+        None => get_default_device(options.device_type),
+    }
+}
+
+fn get_default_device(device_type: DeviceType) -> Result<Device, Error> {
+    match device_type {
+        DeviceType::Input => cpvc::get_default_input_device(),
+        _ => cpvc::get_default_output_device(),
+    }
+}
+
+// Accepts 0.5 or 50%, a leading + or - makes the value relative to the current volume
+fn parse_volume(value: &str, device: &Device) -> Result<f32, CliError> {
+    let relative = value.starts_with('+') || value.starts_with('-');
+    let number = value.trim_start_matches('+');
+    let parsed = match number.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().map(|percent| percent / 100.0),
+        None => number.parse::<f32>(),
+    };
+    let volume = parsed.map_err(|_| CliError::Usage(format!("Invalid volume {}", value)))?;
+    if !volume.is_finite() {
+        return Err(CliError::Usage(format!("Invalid volume {}", value)));
+    }
+    if relative {
+        // Relative changes stop at the ends of the range instead of failing
+        Ok((device.get_vol()? + volume).clamp(0.0, device.get_max_vol()?))
+    } else {
+        Ok(volume)
+    }
+}
+
+fn list(options: &Options) -> Result<(), CliError> {
+    let state = cpvc::system_state()?;
+    let devices = state.outputs.iter().chain(state.inputs.iter());
+
+    if options.json {
+        let entries = devices.map(descriptor_json).collect::<Vec<_>>();
+        println!("[{}]", entries.join(","));
+    } else {
+        for descriptor in devices {
+            println!("{}\t{}\t{}\t{}{}",
+                type_name(descriptor.device_type),
+                descriptor.uid,
+                descriptor.name,
+                state_text(descriptor),
+                if descriptor.default { "\tdefault" } else { "" },
+            );
+        }
+    }
+    Ok(())
+}
+
+fn print_device(options: &Options, device: &Device) -> Result<(), CliError> {
+    let descriptor = device.get_descriptor()?;
+    if options.json {
+        println!("{}", descriptor_json(&descriptor));
+    } else {
+        println!("{}\t{}", descriptor.name, state_text(&descriptor));
+    }
+    Ok(())
+}
+
+fn state_text(descriptor: &DeviceDescriptor) -> String {
+    format!("{:.0}%{}", descriptor.volume * 100.0, if descriptor.mute { " muted" } else { "" })
+}
+
+fn descriptor_json(descriptor: &DeviceDescriptor) -> String {
+    format!("{{\"uid\":{},\"name\":{},\"type\":\"{}\",\"volume\":{},\"mute\":{},\"default\":{}}}",
+        json_string(&descriptor.uid),
+        json_string(&descriptor.name),
+        type_name(descriptor.device_type),
+        descriptor.volume,
+        descriptor.mute,
+        descriptor.default,
+    )
+}
+
+fn type_name(device_type: DeviceType) -> &'static str {
+    match device_type {
+        DeviceType::Input => "input",
+        DeviceType::Output => "output",
+        DeviceType::None => "none",
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            character if character.is_control() => escaped.push_str(&format!("\\u{:04x}", character as u32)),
+            character => escaped.push(character),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -214,6 +214,25 @@ mod tests {
         assert!(false);
     }
 
+    #[test]
+    fn get_input_status() {
+        dbg!(get_input_devices());
+        dbg!(get_input_volume());
+        dbg!(get_input_mute());
+        assert!(false);
+    }
+
+    #[test]
+    // Change the volume or plug in a device while this runs
+    fn test_subscribe() {
+        let (subscription, receiver) = subscribe_channel().unwrap();
+        while let Ok(event) = receiver.recv_timeout(std::time::Duration::from_secs(10)) {
+            dbg!(event);
+        }
+        subscription.unsubscribe();
+        assert!(false);
+    }
+
     #[cfg(target_os="linux")]
     #[test]
     fn test_alsa_get_device() {
@@ -221,6 +240,134 @@ mod tests {
         assert!(false)
     }
 
+    #[cfg(target_os="linux")]
+    #[test]
+    fn test_alsa_mixer() {
+        use crate::device::DeviceTrait;
+        dbg!(crate::linux::get_backend());
+        for device in crate::alsa::get_cards(crate::DeviceType::Output).unwrap() {
+            dbg!(device.get_uid());
+            dbg!(device.get_name());
+            dbg!(device.get_element_name());
+            dbg!(device.get_channel_vols());
+            dbg!(device.get_mute());
+        }
+        dbg!(crate::alsa::get_vol());
+        assert!(false)
+    }
+
+    // Runs against a headless `pipewire` + `wireplumber` session, e.g. with a null sink
+    #[cfg(all(target_os="linux", feature="pipewire"))]
+    #[test]
+    fn test_pipewire_nodes() {
+        use crate::device::DeviceTrait;
+        dbg!(crate::pipewire::is_running());
+        dbg!(crate::linux::get_backend());
+        let device = crate::pipewire::get_default_output_dev().unwrap();
+        dbg!(device.get_uid());
+        dbg!(device.get_name());
+        dbg!(device.get_channel_vols());
+        dbg!(device.set_vol(0.4));
+        dbg!(device.get_vol());
+        dbg!(device.set_mute(true));
+        dbg!(device.get_mute());
+        dbg!(device.set_mute(false));
+        dbg!(device.set_default(false));
+        assert!(false)
+    }
+
+    // Runs against `jackd -d dummy`
+    #[cfg(all(target_os="linux", feature="jack"))]
+    #[test]
+    fn test_jack_gain() {
+        use crate::device::DeviceTrait;
+        dbg!(crate::jack::is_running());
+        let device = crate::jack::get_default_output_dev().unwrap();
+        dbg!(device.get_uid());
+        dbg!(device.get_port_names());
+        dbg!(device.set_vol(0.5));
+        dbg!(device.get_channel_vols());
+        dbg!(device.set_mute(true));
+        dbg!(device.get_vol());
+        crate::jack::close_gain_client();
+        assert!(false)
+    }
+
+    #[cfg(target_os="linux")]
+    #[test]
+    fn test_pulse_session() {
+        use crate::device::DeviceTrait;
+
+        let session = pulseaudio::session::PulseSession::new().unwrap();
+        let device = session.get_default_output_dev().unwrap();
+        for step in 0..60 {
+            dbg!(device.set_vol(step as f32 / 100.0));
+        }
+        dbg!(device.get_vol());
+        assert!(false)
+    }
+
+    #[cfg(target_os="linux")]
+    #[test]
+    fn test_application_streams() {
+        let streams = pulseaudio::stream::get_application_streams().unwrap();
+        for stream in &streams {
+            dbg!(stream);
+            dbg!(stream.get_vol());
+            dbg!(stream.get_mute());
+        }
+        assert!(false)
+    }
+
+    #[cfg(target_os="linux")]
+    #[test]
+    fn test_set_default_output() {
+        let devices = pulseaudio::get_device_identifiers().unwrap();
+        let previous = crate::get_default_output_device().unwrap();
+        for (uid, _name) in devices {
+            let device = crate::Device::from_uid(uid).unwrap();
+            dbg!(crate::set_default_output_device(&device));
+            dbg!(crate::get_default_output_device().unwrap().get_name());
+        }
+        dbg!(previous.make_default(true));
+        assert!(false)
+    }
+
+    #[cfg(target_os="linux")]
+    #[test]
+    fn test_move_stream() {
+        let devices = pulseaudio::get_device_identifiers().unwrap();
+        let mut streams = pulseaudio::stream::get_application_streams().unwrap();
+        for stream in streams.iter_mut() {
+            for (uid, _name) in &devices {
+                let device = crate::Device::from_uid(uid.clone()).unwrap();
+                dbg!(stream.move_to(&device));
+                dbg!(stream.get_sink_uid());
+            }
+        }
+        assert!(false)
+    }
+
+    #[cfg(target_os="linux")]
+    #[test]
+    fn test_card_profiles() {
+        let cards = pulseaudio::card::get_cards().unwrap();
+        for card in &cards {
+            dbg!(card.get_name());
+            dbg!(card.get_description());
+            dbg!(card.get_profiles());
+            let previous = card.get_active_profile().unwrap();
+            for profile in card.get_profiles().into_iter().filter(|profile| profile.available) {
+                dbg!(card.set_profile(profile.name));
+                dbg!(card.get_active_profile());
+            }
+            if let Some(previous) = previous {
+                dbg!(card.set_profile(previous.name));
+            }
+        }
+        assert!(false)
+    }
+
     #[cfg(target_os="macos")] 
     #[test]
     fn get_dev_hw_name() {
@@ -358,6 +358,10 @@ pub mod coreaudio {
         }
     }
 
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        get_device_identifiers()?.into_iter().map(|(id, _name)| hw_id_to_uid(id)).collect()
+    }
+
     pub fn get_vol() -> Result<f32, Error> {
         let output_dev = get_default_output_device()?;
         output_dev.get_vol()
@@ -402,6 +406,10 @@ pub mod coreaudio {
         Err(Error::PlatformUnsupported)
     }
 
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
     pub fn get_vol() -> Result<f32, Error> {
         Err(Error::PlatformUnsupported)
     }
@@ -1,6 +1,6 @@
 use cpal::traits::DeviceTrait;
 pub use cpal::*;
-use crate::{device::Device, error::Error, get_default_output_device, pulseaudio};
+use crate::{device::Device, error::Error, get_default_output_device, linux};
 
 pub trait VolumeControlExt {
     fn default_volume_control(&self) -> Result<VolControl, Error>;
@@ -41,7 +41,7 @@ impl VolControl {
                             // println!("card{card_str} id_str {id_str}" );
                             if let Some(card_num) = device_id.id().chars().nth(card_str + 5).map(|c| c.to_string())
                                 && let Some(id_num) = device_id.id().chars().nth(id_str + 4).map(|c| c.to_string()) {
-                                match pulseaudio::convert_alsa_id(card_num, id_num) {
+                                match linux::convert_alsa_id(card_num, id_num) {
                                     Ok(dev_id) => {
                                         Device::from_uid(dev_id)
                                     },
@@ -66,6 +66,19 @@ impl VolControl {
             "wasapi" => {
                 Device::from_uid(device_id.id().to_string())
             },
+            "jack" => {
+                #[cfg(all(target_os = "linux", feature = "jack", not(feature = "mock")))] {
+                    // The id is the name of cpal's client, its ports (and so its direction) exist while a stream of the device runs
+                    let device = match crate::jack::get_client_type(device_id.id()) {
+                        Ok(crate::DeviceType::Input) => crate::jack::get_default_input_dev(),
+                        Ok(_) => crate::jack::get_default_output_dev(),
+                        Err(error) => Err(error),
+                    };
+                    device.map(|device| Device::from_device(linux::LinuxDevice::Jack(device)))
+                }
+                #[cfg(not(all(target_os = "linux", feature = "jack", not(feature = "mock"))))]
+                Err(Error::PlatformUnsupported)
+            },
             _ => {
                 Err(Error::PlatformUnsupported)
             }
@@ -0,0 +1,74 @@
+use std::sync::Mutex;
+use crate::error::Error;
+
+/// dB covered by `VolumeCurve::Logarithmic` when no other range is picked
+pub const DEFAULT_DB_RANGE: f32 = 60.0;
+
+static VOLUME_CURVE: Mutex<VolumeCurve> = Mutex::new(VolumeCurve::Cubic);
+
+/// Mapping between a volume value (e.g. a slider position) and the amplitude the device outputs
+///
+/// Every curve maps `0.0` to silence and `1.0` to full scale, boosted values above `1.0` amplify
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VolumeCurve {
+    /// Volume is the amplitude itself, `0.5` is -6 dB
+    Linear,
+    /// Amplitude is the cube of the volume, `0.5` is about -18 dB, the mapping PulseAudio and PipeWire sliders use
+    #[default]
+    Cubic,
+    /// Volume is spread evenly over `range` dB below full scale, `0.5` is `-range / 2` dB
+    Logarithmic { range: f32 },
+}
+
+impl VolumeCurve {
+    pub fn to_amplitude(&self, volume: f32) -> f32 {
+        match self {
+            VolumeCurve::Linear => volume,
+            VolumeCurve::Cubic => volume.powi(3),
+            VolumeCurve::Logarithmic { range } => {
+                if volume <= 0.0 { 0.0 } else { 10f32.powf((volume - 1.0) * range / 20.0) }
+            }
+        }
+    }
+
+    pub fn from_amplitude(&self, amplitude: f32) -> f32 {
+        match self {
+            VolumeCurve::Linear => amplitude,
+            VolumeCurve::Cubic => amplitude.cbrt(),
+            VolumeCurve::Logarithmic { range } => {
+                if amplitude <= 0.0 { 0.0 } else { (1.0 + 20.0 * amplitude.log10() / range).max(0.0) }
+            }
+        }
+    }
+
+    /// Converts `volume` on this curve to the value giving the same amplitude on `curve`
+    ///
+    /// Negative and NaN volumes are returned unchanged so the backend rejects them
+    pub fn convert(&self, volume: f32, curve: VolumeCurve) -> f32 {
+        if *self == curve || volume.is_nan() || volume < 0.0 {
+            return volume;
+        }
+        curve.from_amplitude(self.to_amplitude(volume))
+    }
+
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        match self {
+            VolumeCurve::Logarithmic { range } if !range.is_finite() || *range <= 0.0 => {
+                Err(Error::VolumeSetFailed(format!("Volume curve range {} dB is not above 0.0 dB", range)))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Sets the curve volumes of the crate functions and devices without their own curve use, `Cubic` by default
+pub fn set_volume_curve(curve: VolumeCurve) -> Result<(), Error> {
+    curve.check()?;
+    *VOLUME_CURVE.lock().unwrap() = curve;
+    Ok(())
+}
+
+pub fn get_volume_curve() -> VolumeCurve {
+    *VOLUME_CURVE.lock().unwrap()
+}
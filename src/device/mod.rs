@@ -1,20 +1,178 @@
-use crate::error::{self, Error};
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+use crate::{DeviceType, curve::{self, VolumeCurve}, error::{self, Error}, fade::{self, Fade}};
 
-#[cfg(target_os = "linux")]
-use crate::pulseaudio::device::PulseAudioDevice;
-#[cfg(target_os = "linux")]
-pub type Device = UnifiedDevice<PulseAudioDevice>;
+#[cfg(all(target_os = "linux", not(feature = "mock")))]
+use crate::linux::LinuxDevice;
+#[cfg(all(target_os = "linux", not(feature = "mock")))]
+pub type Device = UnifiedDevice<LinuxDevice>;
 
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "mock")))]
 use crate::wasapi::device::WASAPIDevice;
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "mock")))]
 pub type Device = UnifiedDevice<WASAPIDevice>;
 
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "mock")))]
 use crate::coreaudio::device::CoreAudioDevice;
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "mock")))]
 pub type Device = UnifiedDevice<CoreAudioDevice>;
 
+#[cfg(feature = "mock")]
+use crate::mock::MockDevice;
+#[cfg(feature = "mock")]
+pub type Device = UnifiedDevice<MockDevice>;
+
+/// Ceiling of boosted devices until changed with `set_max_vol`, PulseAudio's `PA_VOLUME_UI_MAX` (+11 dB)
+pub const DEFAULT_MAX_VOL: f32 = 99957.0 / 65536.0;
+
+// Boost settings are kept per UID so they survive recreating the device
+struct BoostSettings {
+    allowed: bool,
+    max_vol: f32,
+}
+
+static BOOST_SETTINGS: Mutex<BTreeMap<String, BoostSettings>> = Mutex::new(BTreeMap::new());
+
+/// Allows or forbids volumes above `1.0` on the device with `uid`, forbidden by default
+pub fn set_boost_allowed(uid: &str, allowed: bool) {
+    BOOST_SETTINGS.lock().unwrap()
+        .entry(uid.to_string())
+        .or_insert(BoostSettings { allowed, max_vol: DEFAULT_MAX_VOL })
+        .allowed = allowed;
+}
+
+pub fn is_boost_allowed(uid: &str) -> bool {
+    BOOST_SETTINGS.lock().unwrap().get(uid).is_some_and(|settings| settings.allowed)
+}
+
+/// Sets the highest volume accepted while boosting is allowed, at least `1.0`
+pub fn set_max_vol(uid: &str, max_vol: f32) -> Result<(), Error> {
+    if !max_vol.is_finite() || max_vol < 1.0 {
+        return Err(Error::VolumeSetFailed(format!("Volume ceiling {} below 1.0", max_vol)));
+    }
+    BOOST_SETTINGS.lock().unwrap()
+        .entry(uid.to_string())
+        .or_insert(BoostSettings { allowed: false, max_vol })
+        .max_vol = max_vol;
+    Ok(())
+}
+
+/// Highest volume `set_vol` accepts for the device with `uid`, `1.0` unless boosting is allowed
+pub fn get_max_vol(uid: &str) -> f32 {
+    match BOOST_SETTINGS.lock().unwrap().get(uid) {
+        Some(settings) if settings.allowed => settings.max_vol,
+        _ => 1.0,
+    }
+}
+
+// Backends check every volume they are asked to set, nothing is clamped or wrapped silently
+pub(crate) fn check_vol(uid: &str, value: f32) -> Result<(), Error> {
+    let max_vol = get_max_vol(uid);
+    if value.is_nan() || value < 0.0 {
+        Err(Error::VolumeSetFailed(format!("Volume {} outside of 0.0 to {}", value, max_vol)))
+    } else if value > 1.0 && !is_boost_allowed(uid) {
+        Err(Error::VolumeSetFailed(format!("Volume {} above 1.0, boosting is not allowed on {}", value, uid)))
+    } else if value > max_vol {
+        Err(Error::VolumeSetFailed(format!("Volume {} above the {} ceiling of {}", value, max_vol, uid)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Volume of a single channel of a device
+///
+/// `position` uses the platform naming of the channel (e.g. "front-left")
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelVolume {
+    pub position: String,
+    pub volume: f32,
+}
+
+/// Jack detection state of a port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PortAvailability {
+    /// Port does not support jack detection
+    Unknown,
+    Unplugged,
+    Plugged,
+}
+
+/// Kind of connector behind a port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PortType {
+    Unknown,
+    Speaker,
+    Headphones,
+    Headset,
+    Microphone,
+    Line,
+    Hdmi,
+    Spdif,
+}
+
+/// Physical connection of a device (e.g. "Speakers" and "Headphones" on the same card)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Port {
+    pub name: String,
+    pub description: String,
+    /// Higher priority ports are preferred as default
+    pub priority: u32,
+    pub availability: PortAvailability,
+    pub port_type: PortType,
+}
+
+/// Backend metadata of a device, fields the backend cannot supply are `None`
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceInfo {
+    /// Driver or module behind the device (e.g. "module-alsa-card.c", "snd_hda_intel")
+    pub driver: Option<String>,
+    /// Sample format name (e.g. "s16le", "float32le")
+    pub sample_format: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+    /// Channel positions, named like `ChannelVolume::position`
+    pub channel_map: Option<Vec<String>>,
+    /// Index of the sound card the device belongs to
+    pub card: Option<u32>,
+    /// Volume changes reach the hardware instead of being applied in software
+    pub hw_volume: Option<bool>,
+    /// Volume maps to real dB values, see `DeviceTrait::has_db_volume`
+    pub db_volume: Option<bool>,
+    /// Device can report its latency
+    pub latency: Option<bool>,
+    /// Number of distinct volume steps
+    pub volume_steps: Option<u32>,
+    /// Volume at which the hardware is at 0 dB, `1.0` when there is no hardware volume
+    pub base_volume: Option<f32>,
+    /// Bus the device is connected through (e.g. "pci", "usb", "bluetooth")
+    pub bus: Option<String>,
+    /// Kind of hardware (e.g. "internal", "speaker", "headset")
+    pub form_factor: Option<String>,
+    /// Freedesktop icon name (e.g. "audio-card-analog-pci")
+    pub icon_name: Option<String>,
+}
+
+/// Snapshot of a device, see `UnifiedDevice::get_descriptor`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceDescriptor {
+    pub uid: String,
+    pub name: String,
+    pub device_type: DeviceType,
+    /// On the device's curve, like `UnifiedDevice::get_vol`
+    pub volume: f32,
+    pub mute: bool,
+    /// Device is the default of its direction
+    pub default: bool,
+}
+
+/// Creates a device on the thread it is called on, see `DeviceTrait::get_opener`
+pub type DeviceOpener<T> = Box<dyn FnOnce() -> Result<T, Error> + Send>;
+
 pub trait DeviceTrait {
     fn from_name(name: String) -> Result<Self, Error> where Self: Sized {
         Err(Error::PlatformUnsupported)
@@ -32,6 +190,10 @@ pub trait DeviceTrait {
         Err(Error::PlatformUnsupported)
     }
 
+    fn get_device_type(&self) -> Result<DeviceType, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
     fn get_vol(&self) -> Result<f32, Error> {
         Err(Error::PlatformUnsupported)
     }
@@ -48,10 +210,98 @@ pub trait DeviceTrait {
         Err(Error::PlatformUnsupported)
     }
 
+    /// Makes the device the system default for its direction,
+    /// optionally moving active streams onto it
+    fn set_default(&self, move_streams: bool) -> Result<(), Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    fn get_ports(&self) -> Result<Vec<Port>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// `None` when the device has no ports
+    fn get_active_port(&self) -> Result<Option<Port>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// `name` matches `Port::name` of one of `get_ports`
+    fn set_active_port(&self, name: String) -> Result<(), Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    fn get_channel_vols(&self) -> Result<Vec<ChannelVolume>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// `channel` is the index into `get_channel_vols`
+    fn set_channel_vol(&self, channel: usize, value: f32) -> Result<(), Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// Left/right balance from `-1.0` (left) to `1.0` (right)
+    fn get_balance(&self) -> Result<f32, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    fn set_balance(&self, balance: f32) -> Result<(), Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// Front/rear fade from `-1.0` (rear) to `1.0` (front)
+    fn get_fade(&self) -> Result<f32, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    fn set_fade(&self, fade: f32) -> Result<(), Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// Volume of the loudest channel in dB, `0.0` is the device's reference level (dBFS)
+    ///
+    /// Muted devices report `f32::NEG_INFINITY`
+    fn get_vol_db(&self) -> Result<f32, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// Scales every channel so the loudest one is at `db`, keeping balance and fade
+    fn set_vol_db(&self, db: f32) -> Result<(), Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// Reports if the dB values map to the hardware's own gain steps instead of a software approximation
+    fn has_db_volume(&self) -> Result<bool, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    /// Reports if the backend can amplify above `1.0` at all
+    fn supports_boost(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Curve of the values `get_vol` and `set_vol` work with, the tapered scalars of most platforms are close to cubic
+    fn get_native_curve(&self) -> Result<VolumeCurve, Error> {
+        Ok(VolumeCurve::Cubic)
+    }
+
+    /// Driver, sample spec, flags and similar metadata, every field defaults to `None`
+    fn get_info(&self) -> Result<DeviceInfo, Error> {
+        Ok(DeviceInfo::default())
+    }
+
+    /// Opener for the same device on another thread, fades use it to run in the background
+    ///
+    /// Looks the device up by UID again unless the backend can share its handle
+    fn get_opener(&self) -> Result<DeviceOpener<Self>, Error> where Self: Sized + 'static {
+        let uid = self.get_uid()?;
+        Ok(Box::new(move || Self::from_uid(uid)))
+    }
+
 }
 
 pub struct UnifiedDevice<T: DeviceTrait> {
     device: T,
+    curve: Option<VolumeCurve>,
 }
 
 impl<T> UnifiedDevice<T> 
@@ -60,7 +310,8 @@ where
 {
     pub fn from_device(device: T) -> Self {
         UnifiedDevice {
-            device
+            device,
+            curve: None,
         }
     }
 
@@ -75,7 +326,8 @@ where
                         return Err(error)
                     }
                 }
-            }
+            },
+            curve: None,
         })
     }
 
@@ -90,7 +342,8 @@ where
                         return Err(error)
                     }
                 }
-            }
+            },
+            curve: None,
         })
     }
 
@@ -98,12 +351,21 @@ where
         self.device.get_name()
     }
 
+    pub fn get_uid(&self) -> Result<String, Error> {
+        self.device.get_uid()
+    }
+
+    pub fn get_device_type(&self) -> Result<DeviceType, Error> {
+        self.device.get_device_type()
+    }
+
+    /// Volume on the device's curve, see `get_curve`
     pub fn get_vol(&self) -> Result<f32, Error> {
-        self.device.get_vol()
+        self.from_native(self.device.get_vol()?)
     }
 
     pub fn set_vol(&self, vol: f32) -> Result<(), Error> {
-        self.device.set_vol(vol)
+        self.device.set_vol(self.to_native(vol)?)
     }
 
     pub fn get_mute(&self) -> Result<bool, Error> {
@@ -114,6 +376,159 @@ where
         self.device.set_mute(mute)
     }
 
+    pub fn make_default(&self, move_streams: bool) -> Result<(), Error> {
+        self.device.set_default(move_streams)
+    }
+
+    pub fn get_ports(&self) -> Result<Vec<Port>, Error> {
+        self.device.get_ports()
+    }
+
+    pub fn get_active_port(&self) -> Result<Option<Port>, Error> {
+        self.device.get_active_port()
+    }
+
+    pub fn set_active_port(&self, name: String) -> Result<(), Error> {
+        self.device.set_active_port(name)
+    }
+
+    pub fn get_channel_vols(&self) -> Result<Vec<ChannelVolume>, Error> {
+        self.device.get_channel_vols()?.into_iter()
+            .map(|channel| Ok(ChannelVolume {
+                volume: self.from_native(channel.volume)?,
+                ..channel
+            }))
+            .collect()
+    }
+
+    pub fn set_channel_vol(&self, channel: usize, vol: f32) -> Result<(), Error> {
+        self.device.set_channel_vol(channel, self.to_native(vol)?)
+    }
+
+    pub fn get_balance(&self) -> Result<f32, Error> {
+        self.device.get_balance()
+    }
+
+    pub fn set_balance(&self, balance: f32) -> Result<(), Error> {
+        self.device.set_balance(balance)
+    }
+
+    pub fn get_fade(&self) -> Result<f32, Error> {
+        self.device.get_fade()
+    }
+
+    pub fn set_fade(&self, fade: f32) -> Result<(), Error> {
+        self.device.set_fade(fade)
+    }
+
+    pub fn get_vol_db(&self) -> Result<f32, Error> {
+        self.device.get_vol_db()
+    }
+
+    pub fn set_vol_db(&self, db: f32) -> Result<(), Error> {
+        self.device.set_vol_db(db)
+    }
+
+    pub fn has_db_volume(&self) -> Result<bool, Error> {
+        self.device.has_db_volume()
+    }
+
+    pub fn supports_boost(&self) -> Result<bool, Error> {
+        self.device.supports_boost()
+    }
+
+    /// Allows volumes above `1.0` up to `get_max_vol`, fails if the backend cannot boost
+    pub fn set_boost_allowed(&self, allowed: bool) -> Result<(), Error> {
+        if allowed && !self.device.supports_boost()? {
+            return Err(Error::VolumeSetFailed(format!("{} cannot be boosted above 1.0", self.device.get_name()?)));
+        }
+        set_boost_allowed(&self.device.get_uid()?, allowed);
+        Ok(())
+    }
+
+    pub fn is_boost_allowed(&self) -> Result<bool, Error> {
+        Ok(is_boost_allowed(&self.device.get_uid()?))
+    }
+
+    pub fn set_max_vol(&self, max_vol: f32) -> Result<(), Error> {
+        set_max_vol(&self.device.get_uid()?, self.to_native(max_vol)?)
+    }
+
+    pub fn get_max_vol(&self) -> Result<f32, Error> {
+        self.from_native(get_max_vol(&self.device.get_uid()?))
+    }
+
+    /// Curve volumes of this device are on, the crate wide `curve::get_volume_curve` unless set with `set_curve`
+    pub fn get_curve(&self) -> VolumeCurve {
+        self.curve.unwrap_or_else(curve::get_volume_curve)
+    }
+
+    /// Overrides the crate wide curve for this device, `None` follows it again
+    pub fn set_curve(&mut self, curve: Option<VolumeCurve>) -> Result<(), Error> {
+        if let Some(curve) = curve.as_ref() {
+            curve.check()?;
+        }
+        self.curve = curve;
+        Ok(())
+    }
+
+    pub fn get_native_curve(&self) -> Result<VolumeCurve, Error> {
+        self.device.get_native_curve()
+    }
+
+    fn to_native(&self, vol: f32) -> Result<f32, Error> {
+        Ok(self.get_curve().convert(vol, self.device.get_native_curve()?))
+    }
+
+    fn from_native(&self, vol: f32) -> Result<f32, Error> {
+        Ok(self.device.get_native_curve()?.convert(vol, self.get_curve()))
+    }
+
+    /// Backend metadata such as the driver, sample spec, bus and icon name
+    pub fn get_info(&self) -> Result<DeviceInfo, Error> {
+        self.device.get_info()
+    }
+
+    /// Gathers uid, name, direction, volume, mute and default flag in one struct
+    pub fn get_descriptor(&self) -> Result<DeviceDescriptor, Error> {
+        let uid = self.device.get_uid()?;
+        let device_type = self.device.get_device_type()?;
+        let default_uid = match device_type {
+            DeviceType::Input => crate::get_default_input_device(),
+            _ => crate::get_default_output_device(),
+        }.and_then(|device| device.get_uid());
+        Ok(DeviceDescriptor {
+            default: default_uid.is_ok_and(|default_uid| default_uid == uid),
+            uid,
+            name: self.device.get_name()?,
+            device_type,
+            volume: self.get_vol()?,
+            mute: self.device.get_mute()?,
+        })
+    }
+
+    /// Moves the volume to `target` over `duration` on a background thread
+    ///
+    /// `target` is on the device's curve, the fade steps evenly along `curve` (e.g. `Logarithmic` for even loudness steps),
+    /// a fade started later on the same device supersedes this one, a target `set_vol` would reject fails right away
+    pub fn fade_to(&self, target: f32, duration: Duration, curve: VolumeCurve) -> Result<Fade, Error> where T: 'static {
+        curve.check()?;
+        let uid = self.device.get_uid()?;
+        check_vol(&uid, self.to_native(target)?)?;
+        Ok(fade::start(uid, self.device.get_opener()?, self.get_curve(), target, duration, curve))
+    }
+
+    /// Opens this device again, on another thread if needed, with the same curve
+    #[cfg(feature = "async")]
+    pub(crate) fn get_opener(&self) -> Result<DeviceOpener<Self>, Error> where T: 'static {
+        let open = self.device.get_opener()?;
+        let curve = self.curve;
+        Ok(Box::new(move || Ok(UnifiedDevice {
+            device: open()?,
+            curve,
+        })))
+    }
+
 }
 
 #[cfg(test)]
@@ -127,4 +542,48 @@ mod test {
         dbg!(device.get_mute());
         assert!(false);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unified_balance() {
+        let device = crate::get_default_output_device().unwrap();
+        dbg!(device.get_channel_vols());
+        dbg!(device.set_balance(-0.5));
+        dbg!(device.get_balance());
+        dbg!(device.set_vol(0.4));
+        dbg!(device.get_balance());
+        dbg!(device.get_fade());
+        assert!(false);
+    }
+
+    #[test]
+    fn test_unified_db() {
+        let device = crate::get_default_output_device().unwrap();
+        dbg!(device.has_db_volume());
+        dbg!(device.get_vol_db());
+        dbg!(device.set_vol_db(-12.0));
+        dbg!(device.get_vol_db());
+        dbg!(device.get_vol());
+        assert!(false);
+    }
+
+    #[test]
+    fn test_unified_info() {
+        let device = crate::get_default_output_device().unwrap();
+        dbg!(device.get_info());
+        assert!(false);
+    }
+
+    #[test]
+    fn test_unified_ports() {
+        let device = crate::get_default_output_device().unwrap();
+        dbg!(device.get_ports());
+        dbg!(device.get_active_port());
+        if let Ok(ports) = device.get_ports() {
+            for port in ports {
+                dbg!(device.set_active_port(port.name));
+                dbg!(device.get_active_port());
+            }
+        }
+        assert!(false);
+    }
+}
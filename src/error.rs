@@ -1,4 +1,5 @@
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Error {
     DeviceNotFound,
     DeviceAccessFailed(String),
@@ -7,6 +8,8 @@ pub enum Error {
     VolumeSetFailed(String),
     MuteSetFailed(String),
     PlatformUnsupported,
+    /// libpulse error code (a `pa_error_code_t`) with its description
+    PulseAudio(i32, String),
     Placeholder,
     External(String)
 }
\ No newline at end of file
@@ -0,0 +1,55 @@
+use crate::{DeviceType, device::PortAvailability, pulseaudio::options::ClientIdentity};
+
+/// Change notification delivered to subscribers
+///
+/// `uid` matches `Device::get_uid` of the affected device
+///
+/// `origin` is the identity this process connected with when the change was made through cpvc,
+/// `None` for changes of other clients and the hardware
+///
+/// PulseAudio does not report who made a change, so there `origin` is a best-effort guess:
+/// a notification is attributed to this process when one of its requests touched the same device
+/// in the second before, each request accounting for one notification
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
+    DeviceAdded { uid: String, device_type: DeviceType },
+    DeviceRemoved { uid: String, device_type: DeviceType },
+    DefaultDeviceChanged { uid: String, device_type: DeviceType, origin: Option<ClientIdentity> },
+    /// `volume` is what `Device::get_vol` reports for a device without its own curve,
+    /// on the curve from `curve::get_volume_curve` and `0.0` while muted
+    VolumeChanged { uid: String, volume: f32, origin: Option<ClientIdentity> },
+    MuteChanged { uid: String, mute: bool, origin: Option<ClientIdentity> },
+    /// A jack was plugged into or removed from `port` of the device
+    PortAvailabilityChanged { uid: String, port: String, availability: PortAvailability },
+    ActivePortChanged { uid: String, port: String, origin: Option<ClientIdentity> },
+}
+
+/// Handle to an active subscription
+///
+/// Events stop being delivered once the handle is dropped or `unsubscribe` is called
+pub struct Subscription {
+    stop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Subscription {
+    pub(crate) fn new(stop: impl FnOnce() + Send + 'static) -> Self {
+        Subscription {
+            stop: Some(Box::new(stop)),
+        }
+    }
+
+    pub fn unsubscribe(mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver}}, thread, time::{Duration, Instant}};
+use crate::{curve::VolumeCurve, device::{DeviceOpener, DeviceTrait, UnifiedDevice}, error::Error};
+
+// Time between volume steps, short enough to sound smooth without flooding the sound server
+const STEP_INTERVAL: Duration = Duration::from_millis(20);
+
+// Fade currently running on each device UID, a new fade replaces the entry and stops the old one
+static RUNNING_FADES: Mutex<BTreeMap<String, Arc<FadeControl>>> = Mutex::new(BTreeMap::new());
+
+/// How a fade ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FadeStatus {
+    /// The device reached the target volume
+    Completed,
+    /// `Fade::cancel` was called, the volume stays at the last step
+    Cancelled,
+    /// Another fade started on the same device
+    Superseded,
+}
+
+#[derive(Default)]
+struct FadeControl {
+    cancelled: AtomicBool,
+    superseded: AtomicBool,
+    finished: AtomicBool,
+}
+
+/// Handle to a fade running on a background thread
+///
+/// Dropping the handle does not stop the fade, call `cancel` for that
+pub struct Fade {
+    control: Arc<FadeControl>,
+    result: Receiver<Result<FadeStatus, Error>>,
+}
+
+impl Fade {
+    /// Stops the fade before its next step
+    pub fn cancel(&self) {
+        self.control.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.control.finished.load(Ordering::SeqCst)
+    }
+
+    /// Blocks until the fade ends, errors are the ones a step ran into
+    pub fn wait(self) -> Result<FadeStatus, Error> {
+        self.result.recv()
+            .unwrap_or(Err(Error::External(format!("Fade thread stopped without a result"))))
+    }
+}
+
+// `device_curve` is the curve `target` is on, the fade itself moves evenly along `curve`
+pub(crate) fn start<T: DeviceTrait + 'static>(uid: String, open: DeviceOpener<T>, device_curve: VolumeCurve, target: f32, duration: Duration, curve: VolumeCurve) -> Fade {
+    let control = Arc::new(FadeControl::default());
+    if let Some(previous) = RUNNING_FADES.lock().unwrap().insert(uid.clone(), Arc::clone(&control)) {
+        previous.superseded.store(true, Ordering::SeqCst);
+    }
+
+    let (sender, result) = mpsc::channel();
+    let thread_control = Arc::clone(&control);
+    thread::spawn(move || {
+        // The device is opened once here, every step reuses its connection
+        let status = open().and_then(|device| {
+            let mut device = UnifiedDevice::from_device(device);
+            device.set_curve(Some(device_curve))?;
+            run(&device, &thread_control, target, duration, curve)
+        });
+        let mut running = RUNNING_FADES.lock().unwrap();
+        if running.get(&uid).is_some_and(|control| Arc::ptr_eq(control, &thread_control)) {
+            running.remove(&uid);
+        }
+        drop(running);
+        thread_control.finished.store(true, Ordering::SeqCst);
+        let _ = sender.send(status);
+    });
+
+    Fade {
+        control,
+        result,
+    }
+}
+
+fn run<T: DeviceTrait>(device: &UnifiedDevice<T>, control: &FadeControl, target: f32, duration: Duration, curve: VolumeCurve) -> Result<FadeStatus, Error> {
+    let device_curve = device.get_curve();
+    let start = device_curve.convert(device.get_vol()?, curve);
+    let end = device_curve.convert(target, curve);
+    // Muted devices report 0.0, they fade in from silence and are unmuted by the first audible step,
+    // the same goes for devices muted by a step at 0.0
+    let mut muted = device.get_mute()?;
+    let started = Instant::now();
+    loop {
+        if control.superseded.load(Ordering::SeqCst) {
+            return Ok(FadeStatus::Superseded);
+        }
+        if control.cancelled.load(Ordering::SeqCst) {
+            return Ok(FadeStatus::Cancelled);
+        }
+        // Progress follows the clock, slow steps are skipped over instead of stretching the fade
+        let progress = if duration.is_zero() { 1.0 } else { (started.elapsed().as_secs_f32() / duration.as_secs_f32()).min(1.0) };
+        let volume = if progress >= 1.0 { target } else { curve.convert(start + (end - start) * progress, device_curve) };
+        device.set_vol(volume)?;
+        if volume == 0.0 {
+            // Some backends (PulseAudio) mute instead of setting a zero volume
+            muted = device.get_mute()?;
+        } else if muted {
+            device.set_mute(false)?;
+            muted = false;
+        }
+        if progress >= 1.0 {
+            return Ok(FadeStatus::Completed);
+        }
+        thread::sleep(STEP_INTERVAL);
+    }
+}
@@ -0,0 +1,173 @@
+// #[cfg(not(all(target_os="linux", feature="jack")))]
+#[cfg(all(target_os="linux", feature="jack"))]
+
+mod device {
+
+    use std::sync::Arc;
+    use crate::{DeviceType, curve::VolumeCurve, device::{self, ChannelVolume, DeviceInfo, DeviceTrait}, error::Error, jack::{self, gain::{ChannelGain, GainClient}}};
+
+    /// Physical playback or capture ports of one JACK client (e.g. `system`)
+    pub struct JackDevice {
+        client_name: String,
+        dev_type: DeviceType,
+    }
+
+    impl DeviceTrait for JackDevice {
+
+        // Name is the client name followed by the direction (e.g. "system playback")
+        fn from_name(name: String) -> Result<Self, Error> {
+            jack::get_devices(DeviceType::Output)?.into_iter()
+                .chain(jack::get_devices(DeviceType::Input)?)
+                .find(|device| device.get_name().is_ok_and(|device_name| device_name == name))
+                .ok_or(Error::DeviceNotFound)
+        }
+
+        // UID is the client name and direction (e.g. "system:playback")
+        fn from_uid(uid: String) -> Result<Self, Error> {
+            let (client_name, direction) = uid.split_once(':').ok_or(Error::DeviceNotFound)?;
+            let dev_type = match direction {
+                "playback" => DeviceType::Output,
+                "capture" => DeviceType::Input,
+                _ => return Err(Error::DeviceNotFound),
+            };
+            jack::get_devices(dev_type)?.into_iter()
+                .find(|device| device.client_name == client_name)
+                .ok_or(Error::DeviceNotFound)
+        }
+
+        fn get_name(&self) -> Result<String, Error> {
+            Ok(format!("{} {}", self.client_name, self.get_direction()))
+        }
+
+        fn get_uid(&self) -> Result<String, Error> {
+            Ok(format!("{}:{}", self.client_name, self.get_direction()))
+        }
+
+        fn get_device_type(&self) -> Result<DeviceType, Error> {
+            Ok(self.dev_type)
+        }
+
+        fn get_vol(&self) -> Result<f32, Error> {
+            if self.get_mute()? {
+                return Ok(0.0);
+            }
+            Ok(self.get_channels()?.iter().map(|channel| channel.get_gain()).fold(0.0, f32::max))
+        }
+
+        // Scales every channel so the loudest one matches `value`
+        fn set_vol(&self, value: f32) -> Result<(), Error> {
+            device::check_vol(&self.get_uid()?, value)?;
+            let channels = self.get_channels()?;
+            let max = channels.iter().map(|channel| channel.get_gain()).fold(0.0, f32::max);
+            for channel in channels {
+                let gain = if max > 0.0 { channel.get_gain() / max * value } else { value };
+                channel.set_gain(gain);
+            }
+            Ok(())
+        }
+
+        fn get_mute(&self) -> Result<bool, Error> {
+            Ok(self.get_channels()?.iter().all(|channel| channel.get_mute()))
+        }
+
+        fn set_mute(&self, mute: bool) -> Result<(), Error> {
+            for channel in self.get_channels()? {
+                channel.set_mute(mute);
+            }
+            Ok(())
+        }
+
+        fn get_channel_vols(&self) -> Result<Vec<ChannelVolume>, Error> {
+            Ok(self.get_channels()?.iter()
+                .map(|channel| ChannelVolume {
+                    position: channel.get_port().split_once(':').map(|(_, port)| port).unwrap_or_default().to_string(),
+                    volume: channel.get_gain(),
+                })
+                .collect())
+        }
+
+        fn set_channel_vol(&self, channel: usize, value: f32) -> Result<(), Error> {
+            let channels = self.get_channels()?;
+            let gain = channels.get(channel)
+                .ok_or(Error::VolumeSetFailed(format!("Channel {} out of range, device has {} channels", channel, channels.len())))?;
+            device::check_vol(&self.get_uid()?, value)?;
+            gain.set_gain(value);
+            Ok(())
+        }
+
+        // Software gain goes past unity, `check_vol` enforces the configured ceiling
+        fn supports_boost(&self) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        // Ports carry mono float samples at the server's rate, the gain client is software volume
+        fn get_info(&self) -> Result<DeviceInfo, Error> {
+            let ports = self.get_port_names()?;
+            let client = jack::open_client("cpvc")?;
+            Ok(DeviceInfo {
+                sample_format: Some(format!("float32")),
+                sample_rate: u32::try_from(client.sample_rate()).ok(),
+                channels: Some(ports.len() as u8),
+                channel_map: Some(ports.iter().map(|port| port.split_once(':').map(|(_, port)| port).unwrap_or_default().to_string()).collect()),
+                hw_volume: Some(false),
+                db_volume: Some(false),
+                ..DeviceInfo::default()
+            })
+        }
+
+        // Gain multiplies the samples directly
+        fn get_native_curve(&self) -> Result<VolumeCurve, Error> {
+            Ok(VolumeCurve::Linear)
+        }
+    }
+
+    impl JackDevice {
+        pub(crate) fn new(client_name: String, dev_type: DeviceType) -> Self {
+            JackDevice {
+                client_name,
+                dev_type,
+            }
+        }
+
+        pub fn get_client_name(&self) -> &str {
+            &self.client_name
+        }
+
+        /// Full names of the physical ports behind the device (e.g. "system:playback_1")
+        pub fn get_port_names(&self) -> Result<Vec<String>, Error> {
+            jack::get_device_ports(&self.client_name, self.dev_type)
+        }
+
+        fn get_direction(&self) -> &'static str {
+            match self.dev_type {
+                DeviceType::Input => "capture",
+                _ => "playback",
+            }
+        }
+
+        // Starts the gain client on first use
+        fn get_channels(&self) -> Result<Vec<Arc<ChannelGain>>, Error> {
+            let channels = GainClient::shared()?.get_channels(&self.get_port_names()?);
+            if channels.is_empty() {
+                return Err(Error::DeviceNotFound);
+            }
+            Ok(channels)
+        }
+    }
+
+}
+
+#[cfg(not(all(target_os="linux", feature="jack")))]
+// #[cfg(all(target_os="linux", feature="jack"))]
+
+mod device {
+
+    use crate::device::DeviceTrait;
+
+    pub struct JackDevice {}
+
+    impl DeviceTrait for JackDevice {}
+
+}
+
+pub(crate) use device::*;
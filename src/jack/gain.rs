@@ -0,0 +1,213 @@
+// #[cfg(not(all(target_os="linux", feature="jack")))]
+#[cfg(all(target_os="linux", feature="jack"))]
+
+mod gain {
+
+    use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU32, Ordering}};
+    use ::jack::{AsyncClient, AudioIn, AudioOut, Client, Control, Port, ProcessHandler, ProcessScope};
+    use crate::{DeviceType, debug_eprintln, error::Error, jack};
+
+    const CLIENT_NAME: &str = "cpvc_gain";
+
+    static SHARED_GAIN: Mutex<Option<Arc<GainClient>>> = Mutex::new(None);
+
+    /// Gain and mute of one physical port, shared with the process callback
+    pub struct ChannelGain {
+        port: String,
+        gain: AtomicU32,
+        mute: AtomicBool,
+    }
+
+    impl ChannelGain {
+        fn new(port: String) -> Self {
+            ChannelGain {
+                port,
+                gain: AtomicU32::new(1.0f32.to_bits()),
+                mute: AtomicBool::new(false),
+            }
+        }
+
+        pub fn get_port(&self) -> &str {
+            &self.port
+        }
+
+        pub fn get_gain(&self) -> f32 {
+            f32::from_bits(self.gain.load(Ordering::Relaxed))
+        }
+
+        pub fn set_gain(&self, gain: f32) {
+            self.gain.store(gain.to_bits(), Ordering::Relaxed);
+        }
+
+        pub fn get_mute(&self) -> bool {
+            self.mute.load(Ordering::Relaxed)
+        }
+
+        pub fn set_mute(&self, mute: bool) {
+            self.mute.store(mute, Ordering::Relaxed);
+        }
+
+        fn get_scale(&self) -> f32 {
+            if self.get_mute() { 0.0 } else { self.get_gain() }
+        }
+    }
+
+    // Pair of ports spliced between a physical port and the clients connected to it
+    struct Insert {
+        channel: Arc<ChannelGain>,
+        input: Port<AudioIn>,
+        output: Port<AudioOut>,
+    }
+
+    struct GainProcess {
+        inserts: Vec<Insert>,
+    }
+
+    impl ProcessHandler for GainProcess {
+        fn process(&mut self, _client: &Client, scope: &ProcessScope) -> Control {
+            for insert in self.inserts.iter_mut() {
+                let scale = insert.channel.get_scale();
+                let input = insert.input.as_slice(scope);
+                let output = insert.output.as_mut_slice(scope);
+                for (output, input) in output.iter_mut().zip(input) {
+                    *output = input * scale;
+                }
+            }
+            Control::Continue
+        }
+    }
+
+    // Connection that was replaced by a route through the gain client
+    struct Reroute {
+        source: String,
+        destination: String,
+    }
+
+    /// JACK client applying software gain and mute to every physical port
+    ///
+    /// Clients connected to a physical port when it starts are routed through it instead,
+    /// physical ports and connections made later bypass it until the client is closed and started again
+    pub struct GainClient {
+        client: Mutex<AsyncClient<(), GainProcess>>,
+        channels: Vec<Arc<ChannelGain>>,
+        reroutes: Vec<Reroute>,
+    }
+
+    impl GainClient {
+        pub fn new() -> Result<Arc<Self>, Error> {
+            let client = jack::open_client(CLIENT_NAME)?;
+            let mut inserts = Vec::new();
+            // (physical port, gain input name, gain output name, direction)
+            let mut routes = Vec::new();
+            for dev_type in [DeviceType::Output, DeviceType::Input] {
+                for port in jack::get_physical_ports(&client, dev_type) {
+                    let short_name = port.replace(':', "_");
+                    let input = client.register_port(&format!("{}_in", short_name), AudioIn::default())
+                        .map_err(|e| Error::DeviceAccessFailed(format!("Failed to register gain port for {} {}", port, e)))?;
+                    let output = client.register_port(&format!("{}_out", short_name), AudioOut::default())
+                        .map_err(|e| Error::DeviceAccessFailed(format!("Failed to register gain port for {} {}", port, e)))?;
+                    let input_name = input.name()
+                        .map_err(|e| Error::DeviceAccessFailed(format!("Failed to read gain port name {}", e)))?;
+                    let output_name = output.name()
+                        .map_err(|e| Error::DeviceAccessFailed(format!("Failed to read gain port name {}", e)))?;
+                    let channel = Arc::new(ChannelGain::new(port.clone()));
+                    routes.push((port, input_name, output_name, dev_type));
+                    inserts.push(Insert {
+                        channel,
+                        input,
+                        output,
+                    });
+                }
+            }
+            let channels = inserts.iter().map(|insert| Arc::clone(&insert.channel)).collect();
+
+            let client = client.activate_async((), GainProcess { inserts })
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to activate gain client {}", e)))?;
+            let mut reroutes = Vec::new();
+            for (port, input, output, dev_type) in routes {
+                let peers = client.as_client().port_by_name(&port)
+                    .map(|physical| physical.get_connections())
+                    .unwrap_or_default();
+                for peer in peers {
+                    // Playback: peer -> physical becomes peer -> gain, capture: physical -> peer becomes gain -> peer
+                    let (original, replacement) = match dev_type {
+                        DeviceType::Input => ((port.clone(), peer.clone()), (output.clone(), peer)),
+                        _ => ((peer.clone(), port.clone()), (peer, input.clone())),
+                    };
+                    if let Err(e) = reroute(client.as_client(), &original, &replacement) {
+                        debug_eprintln(&format!("Failed to route {} through the gain client {:?}", original.0, e));
+                        continue;
+                    }
+                    reroutes.push(Reroute {
+                        source: original.0,
+                        destination: original.1,
+                    });
+                }
+                let (source, destination) = match dev_type {
+                    DeviceType::Input => (port, input),
+                    _ => (output, port),
+                };
+                client.as_client().connect_ports_by_name(&source, &destination)
+                    .map_err(|e| Error::DeviceAccessFailed(format!("Failed to connect {} to {} {}", source, destination, e)))?;
+            }
+
+            Ok(Arc::new(GainClient {
+                client: Mutex::new(client),
+                channels,
+                reroutes,
+            }))
+        }
+
+        /// Gain client shared by every JACK device, started on first use
+        pub fn shared() -> Result<Arc<Self>, Error> {
+            let mut shared = SHARED_GAIN.lock().unwrap();
+            if let Some(gain) = shared.as_ref() {
+                return Ok(Arc::clone(gain));
+            }
+            let gain = GainClient::new()?;
+            shared.replace(Arc::clone(&gain));
+            Ok(gain)
+        }
+
+        /// Stops the shared gain client and restores the original connections
+        pub fn close() {
+            SHARED_GAIN.lock().unwrap().take();
+        }
+
+        pub fn get_channels(&self, ports: &[String]) -> Vec<Arc<ChannelGain>> {
+            ports.iter()
+                .filter_map(|port| self.channels.iter().find(|channel| &channel.port == port))
+                .map(Arc::clone)
+                .collect()
+        }
+    }
+
+    impl Drop for GainClient {
+        fn drop(&mut self) {
+            let client = self.client.lock().unwrap();
+            for route in self.reroutes.iter() {
+                if let Err(e) = client.as_client().connect_ports_by_name(&route.source, &route.destination) {
+                    debug_eprintln(&format!("Failed to restore {} -> {} {}", route.source, route.destination, e));
+                }
+            }
+        }
+    }
+
+    // Swaps the `original` connection for `replacement`, both are (source, destination)
+    fn reroute(client: &Client, original: &(String, String), replacement: &(String, String)) -> Result<(), Error> {
+        client.connect_ports_by_name(&replacement.0, &replacement.1)
+            .map_err(|e| Error::DeviceAccessFailed(format!("Failed to connect {} to {} {}", replacement.0, replacement.1, e)))?;
+        client.disconnect_ports_by_name(&original.0, &original.1)
+            .map_err(|e| Error::DeviceAccessFailed(format!("Failed to disconnect {} from {} {}", original.0, original.1, e)))
+    }
+
+}
+
+#[cfg(not(all(target_os="linux", feature="jack")))]
+// #[cfg(all(target_os="linux", feature="jack"))]
+
+mod gain {
+
+}
+
+pub use gain::*;
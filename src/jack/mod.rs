@@ -0,0 +1,169 @@
+pub mod device;
+pub mod gain;
+
+// #[cfg(not(all(target_os="linux", feature="jack")))]
+#[cfg(all(target_os="linux", feature="jack"))]
+// Physical JACK ports grouped per client, volume is software gain applied by the gain client
+pub mod jack {
+    use ::jack::{Client, ClientOptions, PortFlags};
+    use crate::{DeviceType, debug_println, device::DeviceTrait, error::Error, jack::{device::JackDevice, gain::GainClient}};
+
+    /// Opens a client without starting a server, JACK has to be running already
+    pub(crate) fn open_client(name: &str) -> Result<Client, Error> {
+        let (client, status) = Client::new(name, ClientOptions::NO_START_SERVER)
+            .map_err(|e| Error::DeviceAccessFailed(format!("Failed to connect to JACK {}", e)))?;
+        debug_println(&format!("JACK client {} opened {:?}", client.name(), status));
+        Ok(client)
+    }
+
+    // Playback ports are inputs of the JACK graph and capture ports are outputs
+    pub(crate) fn get_physical_ports(client: &Client, dev_type: DeviceType) -> Vec<String> {
+        let direction = match dev_type {
+            DeviceType::Input => PortFlags::IS_OUTPUT,
+            _ => PortFlags::IS_INPUT,
+        };
+        client.ports(None, Some(::jack::jack_sys::FLOAT_MONO_AUDIO), direction | PortFlags::IS_PHYSICAL)
+    }
+
+    /// Reports if a JACK server accepts clients
+    pub fn is_running() -> bool {
+        open_client("cpvc").is_ok()
+    }
+
+    /// Gathers one device per client owning physical ports (usually just `system`)
+    pub fn get_devices(dev_type: DeviceType) -> Result<Vec<JackDevice>, Error> {
+        let client = open_client("cpvc")?;
+        let mut devices: Vec<JackDevice> = Vec::new();
+        for port in get_physical_ports(&client, dev_type) {
+            let Some((owner, _)) = port.split_once(':') else {
+                continue;
+            };
+            if !devices.iter().any(|device| device.get_client_name() == owner) {
+                devices.push(JackDevice::new(owner.to_string(), dev_type));
+            }
+        }
+        Ok(devices)
+    }
+
+    /// Physical ports of `client_name` in `dev_type` direction, each one is a channel of the device
+    pub(crate) fn get_device_ports(client_name: &str, dev_type: DeviceType) -> Result<Vec<String>, Error> {
+        let client = open_client("cpvc")?;
+        let prefix = format!("{}:", client_name);
+        Ok(get_physical_ports(&client, dev_type).into_iter()
+            .filter(|port| port.starts_with(&prefix))
+            .collect())
+    }
+
+    /// Direction of the device a client (e.g. one of cpal's) stands for, asked from its registered ports
+    ///
+    /// A client reading from the graph through input ports records, one writing through output ports plays
+    pub fn get_client_type(client_name: &str) -> Result<DeviceType, Error> {
+        let client = open_client("cpvc")?;
+        let prefix = format!("{}:", client_name);
+        let owned = |flags: PortFlags| client.ports(None, Some(::jack::jack_sys::FLOAT_MONO_AUDIO), flags).into_iter()
+            .any(|port| port.starts_with(&prefix));
+        if owned(PortFlags::IS_INPUT) {
+            Ok(DeviceType::Input)
+        } else if owned(PortFlags::IS_OUTPUT) {
+            Ok(DeviceType::Output)
+        } else {
+            Err(Error::DeviceNotFound)
+        }
+    }
+
+    /// Stops the gain client, clients routed through it are connected to the physical ports again
+    ///
+    /// Call it before exiting, otherwise JACK drops the gain ports along with their connections.
+    /// The next JACK device call starts it again, routing the ports and connections made since
+    pub fn close_gain_client() {
+        GainClient::close();
+    }
+
+    pub fn get_default_output_dev() -> Result<JackDevice, Error> {
+        get_devices(DeviceType::Output)?.into_iter().next().ok_or(Error::DeviceNotFound)
+    }
+
+    pub fn get_default_input_dev() -> Result<JackDevice, Error> {
+        get_devices(DeviceType::Input)?.into_iter().next().ok_or(Error::DeviceNotFound)
+    }
+
+    // Volume Controls
+    pub fn get_sound_devices() -> Result<Vec<String>, Error> {
+        get_devices(DeviceType::Output)?.iter().map(|device| device.get_name()).collect()
+    }
+
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        get_devices(DeviceType::Output)?.iter().map(|device| device.get_uid()).collect()
+    }
+
+    pub fn get_vol() -> Result<f32, Error> {
+        get_default_output_dev()?.get_vol()
+    }
+
+    pub fn set_vol(value: f32) -> Result<(), Error> {
+        get_default_output_dev()?.set_vol(value)
+    }
+
+    pub fn get_mute() -> Result<bool, Error> {
+        get_default_output_dev()?.get_mute()
+    }
+
+    pub fn set_mute(state: bool) -> Result<(), Error> {
+        get_default_output_dev()?.set_mute(state)
+    }
+
+    // Input Controls
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        get_devices(DeviceType::Input)?.iter().map(|device| device.get_name()).collect()
+    }
+
+    pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+        get_devices(DeviceType::Input)?.iter().map(|device| device.get_uid()).collect()
+    }
+
+    pub fn get_input_vol() -> Result<f32, Error> {
+        get_default_input_dev()?.get_vol()
+    }
+
+    pub fn set_input_vol(value: f32) -> Result<(), Error> {
+        get_default_input_dev()?.set_vol(value)
+    }
+
+    pub fn get_input_mute() -> Result<bool, Error> {
+        get_default_input_dev()?.get_mute()
+    }
+
+    pub fn set_input_mute(state: bool) -> Result<(), Error> {
+        get_default_input_dev()?.set_mute(state)
+    }
+
+}
+
+#[cfg(not(all(target_os="linux", feature="jack")))]
+// #[cfg(all(target_os="linux", feature="jack"))]
+pub mod jack {
+    use crate::error::Error;
+
+    pub fn is_running() -> bool {
+        false
+    }
+
+    pub fn get_sound_devices() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+}
+
+pub(crate) use self::jack::*;
@@ -3,13 +3,15 @@
 //! Currently, cpvc supports the following platforms
 //! * macOS
 //! * Windows
-//! * Linux (`pulse_audio` only)
+//! * Linux (`pulse_audio`, with a native `pipewire` backend behind the `pipewire` feature and an `alsa` mixer fallback when no server is running)
+//!
+//! Input devices (microphones) are currently only supported on Linux
 //!
 //! To access platform specfic modules, you need to be on the specific OS
 //! This functionality may change in future versions
 //!  * macOS -> `coreaudio`
 //!  * windows -> `wasapi`
-//!  * linux -> `pulseaudio`
+//!  * linux -> `pulseaudio`, `pipewire`, `alsa` (`linux` picks between them), `jack`
 //! 
 //! # Controls Example
 //!
@@ -31,10 +33,15 @@
 //!     
 //!     // Mute default output
 //!     let success = cpvc::set_mute(true);
+//!
+//!     // Get current volume of the default input device in %
+//!     let input_volume: f32 = cpvc::get_input_volume();
 //! }
 //! ```
 
-use crate::{device::{Device, DeviceTrait}, error::Error::{self, PlatformUnsupported}};
+use std::{sync::mpsc::{self, Receiver}, time::Duration};
+
+use crate::{curve::VolumeCurve, device::{Device, DeviceDescriptor}, error::Error::{self, PlatformUnsupported}, event::{Event, Subscription}, fade::Fade, pulseaudio::options::ConnectionOptions, snapshot::{RestoreReport, Snapshot}};
 
 pub mod legacy;
 #[cfg(debug_assertions)]
@@ -45,11 +52,26 @@ pub mod device;
 #[cfg(feature = "cpal")]
 pub mod cpal;
 
+#[cfg(feature = "mock")]
+pub mod mock;
+
 pub mod coreaudio;
 pub mod wasapi;
 pub mod pulseaudio;
+pub mod alsa;
+#[cfg(feature = "pipewire")]
+pub mod pipewire;
+#[cfg(feature = "jack")]
+pub mod jack;
+pub mod linux;
 
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod curve;
 pub mod error;
+pub mod event;
+pub mod fade;
+pub mod snapshot;
 
 #[cfg(feature = "debug")]
 fn debug_eprintln(message: &str){
@@ -83,9 +105,10 @@ pub trait VolumeControl {
     fn set_mute(state: bool) -> Result<(), Error>;
 }
 
+/// Direction of an audio device
 #[derive(Debug, Clone, Copy, PartialEq)]
-#[allow(dead_code)]
-enum DeviceType {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceType {
     Input,
     Output,
     None,
@@ -93,14 +116,17 @@ enum DeviceType {
 
 /// Gathers the human readable device name of each output device detected
 pub fn try_get_sound_devices() -> Result<Vec<String>, Error> {
-    #[cfg(target_os="macos")] {
+    #[cfg(feature="mock")] {
+        return mock::get_sound_devices();
+    }
+    #[cfg(all(target_os="macos", not(feature="mock")))] {
         return coreaudio::get_sound_devices();
     }
-    #[cfg(target_os="windows")] {
+    #[cfg(all(target_os="windows", not(feature="mock")))] {
         return wasapi::get_sound_devices();
     }
-    #[cfg(target_os="linux")] {
-        return pulseaudio::get_sound_devices();
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return linux::get_sound_devices();
     }
     Err(Error::PlatformUnsupported)
 }
@@ -109,19 +135,9 @@ pub fn get_sound_devices() -> Vec<String> {
     try_get_sound_devices().unwrap_or(Vec::new())
 }
 
-/// Gathers the current volume in percent of the default output device
+/// Gathers the current volume in percent of the default output device, on the curve from `curve::get_volume_curve`
 pub fn try_get_system_volume() -> Result<f32, Error> {
-    #[allow(unused_assignments)]
-    #[cfg(target_os="macos")] {
-       return coreaudio::get_vol();
-    }
-    #[cfg(target_os="windows")] {
-        return wasapi::get_vol();
-    }
-    #[cfg(target_os="linux")] {
-        return pulseaudio::get_vol();
-    }
-    Err(PlatformUnsupported)
+    get_default_output_device()?.get_vol()
 }
 
 pub fn get_system_volume() -> f32 {
@@ -132,23 +148,12 @@ pub fn get_system_volume_u8() -> u8 {
     (get_system_volume() * 100.0) as u8
 }
 
-/// Sets the current volume in percent of the default output device
+/// Sets the current volume in percent of the default output device, on the curve from `curve::get_volume_curve`
 /// ## On macOS
 /// `cpvc` needs to mute and unmute the audio device to get the hardware device volume to sync 
 pub fn try_set_system_volume(percent: f32) -> Result<bool, Error> {
-    #[cfg(target_os="macos")] {
-        coreaudio::set_vol(percent)?;
-        return Ok(true);
-    }
-    #[cfg(target_os="windows")] {
-        wasapi::set_vol(percent)?;
-        return Ok(true);
-    }
-    #[cfg(target_os="linux")] {
-        pulseaudio::set_vol(percent)?;
-        return Ok(true);
-    }
-    Err(PlatformUnsupported)
+    get_default_output_device()?.set_vol(percent)?;
+    Ok(true)
 }
 
 pub fn set_system_volume(percent: f32) -> bool {
@@ -163,18 +168,27 @@ pub fn set_system_volume_u8(percent: u8) -> bool {
     set_system_volume(percent as f32 / 100.0)
 }
 
+/// Fades the default output device to `target` over `duration` on a background thread, see `Device::fade_to`
+pub fn fade_system_volume(target: f32, duration: Duration, curve: VolumeCurve) -> Result<Fade, Error> {
+    get_default_output_device()?.fade_to(target, duration, curve)
+}
+
 pub fn try_set_mute(mute: bool) -> Result<bool, Error> {
-    #[cfg(target_os="macos")] {
+    #[cfg(feature="mock")] {
+        mock::set_mute(mute)?;
+        return Ok(true);
+    }
+    #[cfg(all(target_os="macos", not(feature="mock")))] {
         coreaudio::set_mute(mute)?.map();
         return Ok(true);
     }
-    #[cfg(target_os="windows")]
+    #[cfg(all(target_os="windows", not(feature="mock")))]
     {
         wasapi::set_mute(mute)?;
         return Ok(true);
     }
-    #[cfg(target_os="linux")] {
-        pulseaudio::set_mute(mute)?;
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        linux::set_mute(mute)?;
         return Ok(true);
     }
     Err(PlatformUnsupported)
@@ -189,14 +203,17 @@ pub fn set_mute(mute: bool) -> bool {
 }
 
 pub fn try_get_mute() -> Result<bool, Error> {
-    #[cfg(target_os="macos")] {
+    #[cfg(feature="mock")] {
+        return mock::get_mute();
+    }
+    #[cfg(all(target_os="macos", not(feature="mock")))] {
         return coreaudio::get_mute();
     }
-    #[cfg(target_os="windows")] {
+    #[cfg(all(target_os="windows", not(feature="mock")))] {
         return wasapi::get_mute();
     }
-    #[cfg(target_os="linux")] {
-        return pulseaudio::get_mute();
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return linux::get_mute();
     }
     Err(PlatformUnsupported)
 }
@@ -210,15 +227,243 @@ pub fn get_mute() -> bool {
 }
 
 pub fn get_default_output_device() -> Result<Device, Error>{
-    #[cfg(target_os="macos")] {
+    #[cfg(feature="mock")] {
+        return Ok(Device::from_device(mock::get_default_output_dev()?))
+    }
+    #[cfg(all(target_os="macos", not(feature="mock")))] {
         use crate::device::DeviceTrait;
         return Device::from_uid(coreaudio::get_default_output_device()?.get_uid()?)
     }
-    #[cfg(target_os="windows")] {
+    #[cfg(all(target_os="windows", not(feature="mock")))] {
         return Device::from_uid(wasapi::get_default_output_device()?.get_device_uid()?)
     }
-    #[cfg(target_os="linux")] {
-        return Device::from_uid(pulseaudio::get_default_output_dev()?.get_uid()?)
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return Ok(Device::from_device(linux::get_default_output_dev()?))
+    }
+    Err(Error::PlatformUnsupported)
+}
+
+/// Makes `device` the default output device, streams stay on their current device
+pub fn set_default_output_device(device: &Device) -> Result<(), Error> {
+    if device.get_device_type()? != DeviceType::Output {
+        return Err(Error::DeviceAccessFailed(format!("{} is not an output device", device.get_name()?)));
+    }
+    device.make_default(false)
+}
+
+/// Gathers the human readable device name of each input device detected
+/// 
+/// Monitor sources (loopbacks of output devices) are not included
+pub fn try_get_input_devices() -> Result<Vec<String>, Error> {
+    #[cfg(feature="mock")] {
+        return mock::get_input_devices();
+    }
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return linux::get_input_devices();
     }
     Err(Error::PlatformUnsupported)
-}
\ No newline at end of file
+}
+
+pub fn get_input_devices() -> Vec<String> {
+    try_get_input_devices().unwrap_or(Vec::new())
+}
+
+/// Gathers the current volume in percent of the default input device, on the curve from `curve::get_volume_curve`
+pub fn try_get_input_volume() -> Result<f32, Error> {
+    get_default_input_device()?.get_vol()
+}
+
+pub fn get_input_volume() -> f32 {
+    try_get_input_volume().unwrap_or(0.0)
+}
+
+/// Sets the current volume in percent of the default input device, on the curve from `curve::get_volume_curve`
+pub fn try_set_input_volume(percent: f32) -> Result<bool, Error> {
+    get_default_input_device()?.set_vol(percent)?;
+    Ok(true)
+}
+
+pub fn set_input_volume(percent: f32) -> bool {
+    try_set_input_volume(percent).unwrap_or(false)
+}
+
+pub fn try_get_input_mute() -> Result<bool, Error> {
+    #[cfg(feature="mock")] {
+        return mock::get_input_mute();
+    }
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return linux::get_input_mute();
+    }
+    Err(PlatformUnsupported)
+}
+
+pub fn get_input_mute() -> bool {
+    try_get_input_mute().unwrap_or(false)
+}
+
+pub fn try_set_input_mute(mute: bool) -> Result<bool, Error> {
+    #[cfg(feature="mock")] {
+        mock::set_input_mute(mute)?;
+        return Ok(true);
+    }
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        linux::set_input_mute(mute)?;
+        return Ok(true);
+    }
+    Err(PlatformUnsupported)
+}
+
+pub fn set_input_mute(mute: bool) -> bool {
+    try_set_input_mute(mute).unwrap_or(false)
+}
+
+pub fn get_default_input_device() -> Result<Device, Error> {
+    #[cfg(feature="mock")] {
+        return Ok(Device::from_device(mock::get_default_input_dev()?))
+    }
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return Ok(Device::from_device(linux::get_default_input_dev()?))
+    }
+    Err(Error::PlatformUnsupported)
+}
+
+/// Makes `device` the default input device, streams stay on their current device
+pub fn set_default_input_device(device: &Device) -> Result<(), Error> {
+    if device.get_device_type()? != DeviceType::Input {
+        return Err(Error::DeviceAccessFailed(format!("{} is not an input device", device.get_name()?)));
+    }
+    device.make_default(false)
+}
+
+/// Picks the PulseAudio server the crate functions, subscriptions and devices opened by uid or name connect to
+///
+/// Devices of other servers can be opened next to it with `pulseaudio::session::PulseSession::connect`
+///
+/// With the `mock` feature only the identity is used, it becomes the identity of the current `MockSystem`
+pub fn set_connection_options(options: ConnectionOptions) -> Result<(), Error> {
+    #[cfg(feature="mock")] {
+        mock::MockSystem::current().set_identity(options.identity);
+        return Ok(());
+    }
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        pulseaudio::session::set_shared_options(options);
+        // The new server may be reachable where the old one was not
+        linux::reset_backend();
+        return Ok(());
+    }
+    #[allow(unreachable_code)]
+    Err(PlatformUnsupported)
+}
+
+/// Delivers device, default device, volume, mute and port changes to `callback`
+///
+/// The callback runs on a background thread until the returned `Subscription` is dropped
+pub fn subscribe<F>(mut callback: F) -> Result<Subscription, Error>
+where
+    F: FnMut(Event) + Send + 'static
+{
+    let (subscription, receiver) = subscribe_channel()?;
+    std::thread::spawn(move || {
+        for event in receiver {
+            callback(event);
+        }
+    });
+    Ok(subscription)
+}
+
+/// Same as `subscribe`, with events delivered through a `Receiver`
+pub fn subscribe_channel() -> Result<(Subscription, Receiver<Event>), Error> {
+    #[allow(unused_variables)]
+    let (sender, receiver) = mpsc::channel();
+    #[cfg(feature="mock")] {
+        return Ok((mock::subscribe(sender)?, receiver));
+    }
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return Ok((linux::subscribe(sender)?, receiver));
+    }
+    Err(PlatformUnsupported)
+}
+
+/// Every device with its state, see `system_state`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SystemState {
+    pub outputs: Vec<DeviceDescriptor>,
+    /// Empty on platforms without input support
+    pub inputs: Vec<DeviceDescriptor>,
+}
+
+// UIDs of the devices `try_get_sound_devices` lists, names can repeat so devices are looked up by UID
+fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+    #[cfg(feature="mock")] {
+        return mock::get_sound_device_uids();
+    }
+    #[cfg(all(target_os="macos", not(feature="mock")))] {
+        return coreaudio::get_sound_device_uids();
+    }
+    #[cfg(all(target_os="windows", not(feature="mock")))] {
+        return wasapi::get_sound_device_uids();
+    }
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return linux::get_sound_device_uids();
+    }
+    Err(Error::PlatformUnsupported)
+}
+
+// UIDs of the devices `try_get_input_devices` lists
+fn get_input_device_uids() -> Result<Vec<String>, Error> {
+    #[cfg(feature="mock")] {
+        return mock::get_input_device_uids();
+    }
+    #[cfg(all(target_os="linux", not(feature="mock")))] {
+        return linux::get_input_device_uids();
+    }
+    Err(Error::PlatformUnsupported)
+}
+
+/// Snapshot of every output and input device, serializable as a whole with the `serde` feature
+pub fn system_state() -> Result<SystemState, Error> {
+    let outputs = get_sound_device_uids()?.into_iter()
+        .map(|uid| Device::from_uid(uid)?.get_descriptor())
+        .collect::<Result<Vec<_>, Error>>()?;
+    let inputs = match get_input_device_uids() {
+        Ok(uids) => uids.into_iter()
+            .map(|uid| Device::from_uid(uid)?.get_descriptor())
+            .collect::<Result<Vec<_>, Error>>()?,
+        Err(PlatformUnsupported) => Vec::new(),
+        Err(error) => return Err(error),
+    };
+    Ok(SystemState {
+        outputs,
+        inputs,
+    })
+}
+
+/// Captures the volume (per channel), mute and active port of every device, the default devices
+/// and, on PulseAudio, the volume, mute and device of every application stream
+pub fn snapshot() -> Result<Snapshot, Error> {
+    let mut devices = get_sound_device_uids()?.into_iter()
+        .map(snapshot::capture_device)
+        .collect::<Result<Vec<_>, Error>>()?;
+    match get_input_device_uids() {
+        Ok(uids) => {
+            for uid in uids {
+                devices.push(snapshot::capture_device(uid)?);
+            }
+        },
+        Err(PlatformUnsupported) => {},
+        Err(error) => return Err(error),
+    }
+    Ok(Snapshot {
+        devices,
+        default_output: get_default_output_device().and_then(|device| device.get_uid()).ok(),
+        default_input: get_default_input_device().and_then(|device| device.get_uid()).ok(),
+        streams: snapshot::capture_streams()?,
+    })
+}
+
+/// Applies `snapshot` again, devices and streams that are gone are skipped and listed in the report
+pub fn restore(snapshot: &Snapshot) -> Result<RestoreReport, Error> {
+    snapshot::restore(snapshot)
+}
+
@@ -0,0 +1,395 @@
+// #[cfg(not(target_os="linux"))]
+#[cfg(target_os="linux")]
+// Picks PulseAudio when a server answers, then native PipeWire, and falls back to the ALSA mixer otherwise
+mod linux {
+    use std::sync::{Mutex, mpsc::Sender};
+    use crate::{
+        DeviceType, alsa::{self, device::AlsaDevice}, curve::VolumeCurve, debug_println,
+        device::{ChannelVolume, DeviceInfo, DeviceOpener, DeviceTrait, Port},
+        error::Error, event::{Event, Subscription},
+        pulseaudio::{self, device::PulseAudioDevice, session::PulseSession},
+    };
+    #[cfg(feature = "pipewire")]
+    use crate::pipewire::{self, device::PipeWireDevice};
+    #[cfg(feature = "jack")]
+    use crate::jack::{self, device::JackDevice};
+
+    /// Sound system `cpvc` talks to on Linux
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Backend {
+        PulseAudio,
+        #[cfg(feature = "pipewire")]
+        PipeWire,
+        /// Never detected, JACK devices are reached through `jack` or `cpal` explicitly
+        #[cfg(feature = "jack")]
+        Jack,
+        Alsa,
+    }
+
+    // Detected on first use and kept until the server of the detected backend goes away
+    static BACKEND: Mutex<Option<Backend>> = Mutex::new(None);
+
+    /// Backend the crate functions use, PulseAudio (or PipeWire's PulseAudio server) is preferred over native PipeWire and bare ALSA
+    ///
+    /// The backend is detected once, it is only probed again after its server stopped answering
+    pub fn get_backend() -> Backend {
+        let mut backend = BACKEND.lock().unwrap();
+        *backend.get_or_insert_with(detect_backend)
+    }
+
+    /// Forgets the detected backend, the next call detects it again
+    pub fn reset_backend() {
+        BACKEND.lock().unwrap().take();
+        pulseaudio::session::close_shared_session();
+    }
+
+    fn detect_backend() -> Backend {
+        match PulseSession::shared() {
+            Ok(_) => Backend::PulseAudio,
+            Err(e) => {
+                #[cfg(feature = "pipewire")]
+                if pipewire::is_running() {
+                    debug_println(&format!("No PulseAudio server, using PipeWire: {:?}", e));
+                    return Backend::PipeWire;
+                }
+                debug_println(&format!("No PulseAudio server, using ALSA: {:?}", e));
+                Backend::Alsa
+            }
+        }
+    }
+
+    // Errors of a server that went away, the kernel mixer and JACK's explicit devices have none to lose
+    fn is_connection_error(backend: Backend, error: &Error) -> bool {
+        match backend {
+            Backend::PulseAudio => pulseaudio::is_connection_error(error),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => matches!(error, Error::DeviceAccessFailed(_)) && !pipewire::is_running(),
+            #[cfg(feature = "jack")]
+            Backend::Jack => false,
+            Backend::Alsa => false,
+        }
+    }
+
+    fn check_connection<T>(backend: Backend, result: Result<T, Error>) -> Result<T, Error> {
+        if let Err(error) = &result && is_connection_error(backend, error) {
+            debug_println(&format!("Lost the {:?} server, detecting the backend again: {:?}", backend, error));
+            reset_backend();
+        }
+        result
+    }
+
+    // Runs `action` on the active backend
+    fn with_backend<T>(action: impl FnOnce(Backend) -> Result<T, Error>) -> Result<T, Error> {
+        let backend = get_backend();
+        check_connection(backend, action(backend))
+    }
+
+    /// Device of whichever backend is active
+    pub enum LinuxDevice {
+        PulseAudio(PulseAudioDevice),
+        #[cfg(feature = "pipewire")]
+        PipeWire(PipeWireDevice),
+        #[cfg(feature = "jack")]
+        Jack(JackDevice),
+        Alsa(AlsaDevice),
+    }
+
+    macro_rules! dispatch {
+        ($device:expr, $inner:ident => $call:expr) => {
+            check_connection($device.get_backend(), match $device {
+                LinuxDevice::PulseAudio($inner) => $call,
+                #[cfg(feature = "pipewire")]
+                LinuxDevice::PipeWire($inner) => $call,
+                #[cfg(feature = "jack")]
+                LinuxDevice::Jack($inner) => $call,
+                LinuxDevice::Alsa($inner) => $call,
+            })
+        };
+    }
+
+    impl DeviceTrait for LinuxDevice {
+        fn from_name(name: String) -> Result<Self, Error> {
+            with_backend(|backend| match backend {
+                Backend::PulseAudio => PulseAudioDevice::from_name(name).map(LinuxDevice::PulseAudio),
+                #[cfg(feature = "pipewire")]
+                Backend::PipeWire => PipeWireDevice::from_name(name).map(LinuxDevice::PipeWire),
+                #[cfg(feature = "jack")]
+                Backend::Jack => JackDevice::from_name(name).map(LinuxDevice::Jack),
+                Backend::Alsa => AlsaDevice::from_name(name).map(LinuxDevice::Alsa),
+            })
+        }
+
+        fn from_uid(uid: String) -> Result<Self, Error> {
+            with_backend(|backend| match backend {
+                Backend::PulseAudio => PulseAudioDevice::from_uid(uid).map(LinuxDevice::PulseAudio),
+                #[cfg(feature = "pipewire")]
+                Backend::PipeWire => PipeWireDevice::from_uid(uid).map(LinuxDevice::PipeWire),
+                #[cfg(feature = "jack")]
+                Backend::Jack => JackDevice::from_uid(uid).map(LinuxDevice::Jack),
+                Backend::Alsa => AlsaDevice::from_uid(uid).map(LinuxDevice::Alsa),
+            })
+        }
+
+        fn get_name(&self) -> Result<String, Error> {
+            dispatch!(self, device => device.get_name())
+        }
+
+        fn get_uid(&self) -> Result<String, Error> {
+            dispatch!(self, device => device.get_uid())
+        }
+
+        fn get_device_type(&self) -> Result<DeviceType, Error> {
+            dispatch!(self, device => device.get_device_type())
+        }
+
+        fn get_vol(&self) -> Result<f32, Error> {
+            dispatch!(self, device => device.get_vol())
+        }
+
+        fn set_vol(&self, value: f32) -> Result<(), Error> {
+            dispatch!(self, device => device.set_vol(value))
+        }
+
+        fn get_mute(&self) -> Result<bool, Error> {
+            dispatch!(self, device => device.get_mute())
+        }
+
+        fn set_mute(&self, state: bool) -> Result<(), Error> {
+            dispatch!(self, device => device.set_mute(state))
+        }
+
+        fn set_default(&self, move_streams: bool) -> Result<(), Error> {
+            dispatch!(self, device => device.set_default(move_streams))
+        }
+
+        fn get_ports(&self) -> Result<Vec<Port>, Error> {
+            dispatch!(self, device => device.get_ports())
+        }
+
+        fn get_active_port(&self) -> Result<Option<Port>, Error> {
+            dispatch!(self, device => device.get_active_port())
+        }
+
+        fn set_active_port(&self, name: String) -> Result<(), Error> {
+            dispatch!(self, device => device.set_active_port(name))
+        }
+
+        fn get_channel_vols(&self) -> Result<Vec<ChannelVolume>, Error> {
+            dispatch!(self, device => device.get_channel_vols())
+        }
+
+        fn set_channel_vol(&self, channel: usize, value: f32) -> Result<(), Error> {
+            dispatch!(self, device => device.set_channel_vol(channel, value))
+        }
+
+        fn get_balance(&self) -> Result<f32, Error> {
+            dispatch!(self, device => device.get_balance())
+        }
+
+        fn set_balance(&self, balance: f32) -> Result<(), Error> {
+            dispatch!(self, device => device.set_balance(balance))
+        }
+
+        fn get_fade(&self) -> Result<f32, Error> {
+            dispatch!(self, device => device.get_fade())
+        }
+
+        fn set_fade(&self, fade: f32) -> Result<(), Error> {
+            dispatch!(self, device => device.set_fade(fade))
+        }
+
+        fn get_vol_db(&self) -> Result<f32, Error> {
+            dispatch!(self, device => device.get_vol_db())
+        }
+
+        fn set_vol_db(&self, db: f32) -> Result<(), Error> {
+            dispatch!(self, device => device.set_vol_db(db))
+        }
+
+        fn has_db_volume(&self) -> Result<bool, Error> {
+            dispatch!(self, device => device.has_db_volume())
+        }
+
+        fn supports_boost(&self) -> Result<bool, Error> {
+            dispatch!(self, device => device.supports_boost())
+        }
+
+        fn get_native_curve(&self) -> Result<VolumeCurve, Error> {
+            dispatch!(self, device => device.get_native_curve())
+        }
+
+        fn get_info(&self) -> Result<DeviceInfo, Error> {
+            dispatch!(self, device => device.get_info())
+        }
+
+        // Keeps the backend of the device, `from_uid` would only look in the active one
+        fn get_opener(&self) -> Result<DeviceOpener<Self>, Error> {
+            match self {
+                LinuxDevice::PulseAudio(device) => {
+                    let open = device.get_opener()?;
+                    Ok(Box::new(move || open().map(LinuxDevice::PulseAudio)))
+                },
+                #[cfg(feature = "pipewire")]
+                LinuxDevice::PipeWire(device) => {
+                    let open = device.get_opener()?;
+                    Ok(Box::new(move || open().map(LinuxDevice::PipeWire)))
+                },
+                #[cfg(feature = "jack")]
+                LinuxDevice::Jack(device) => {
+                    let open = device.get_opener()?;
+                    Ok(Box::new(move || open().map(LinuxDevice::Jack)))
+                },
+                LinuxDevice::Alsa(device) => {
+                    let open = device.get_opener()?;
+                    Ok(Box::new(move || open().map(LinuxDevice::Alsa)))
+                },
+            }
+        }
+    }
+
+    impl LinuxDevice {
+        pub fn get_backend(&self) -> Backend {
+            match self {
+                LinuxDevice::PulseAudio(_) => Backend::PulseAudio,
+                #[cfg(feature = "pipewire")]
+                LinuxDevice::PipeWire(_) => Backend::PipeWire,
+                #[cfg(feature = "jack")]
+                LinuxDevice::Jack(_) => Backend::Jack,
+                LinuxDevice::Alsa(_) => Backend::Alsa,
+            }
+        }
+    }
+
+    pub fn get_default_output_dev() -> Result<LinuxDevice, Error> {
+        with_backend(|backend| match backend {
+            Backend::PulseAudio => pulseaudio::get_default_output_dev().map(LinuxDevice::PulseAudio),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => pipewire::get_default_output_dev().map(LinuxDevice::PipeWire),
+            #[cfg(feature = "jack")]
+            Backend::Jack => jack::get_default_output_dev().map(LinuxDevice::Jack),
+            Backend::Alsa => alsa::get_default_output_dev().map(LinuxDevice::Alsa),
+        })
+    }
+
+    pub fn get_default_input_dev() -> Result<LinuxDevice, Error> {
+        with_backend(|backend| match backend {
+            Backend::PulseAudio => pulseaudio::get_default_input_dev().map(LinuxDevice::PulseAudio),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => pipewire::get_default_input_dev().map(LinuxDevice::PipeWire),
+            #[cfg(feature = "jack")]
+            Backend::Jack => jack::get_default_input_dev().map(LinuxDevice::Jack),
+            Backend::Alsa => alsa::get_default_input_dev().map(LinuxDevice::Alsa),
+        })
+    }
+
+    /// UID of the device behind ALSA card `alsa_card`, device `alsa_id` for the active backend
+    pub fn convert_alsa_id(alsa_card: String, alsa_id: String) -> Result<String, Error> {
+        with_backend(|backend| match backend {
+            Backend::PulseAudio => pulseaudio::convert_alsa_id(alsa_card, alsa_id),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => Err(Error::PlatformUnsupported),
+            #[cfg(feature = "jack")]
+            Backend::Jack => Err(Error::PlatformUnsupported),
+            Backend::Alsa => Ok(format!("hw:{}", alsa_card)),
+        })
+    }
+
+    // Volume Controls
+    pub fn get_sound_devices() -> Result<Vec<String>, Error> {
+        with_backend(|backend| match backend {
+            Backend::PulseAudio => pulseaudio::get_sound_devices(),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => pipewire::get_sound_devices(),
+            #[cfg(feature = "jack")]
+            Backend::Jack => jack::get_sound_devices(),
+            Backend::Alsa => alsa::get_sound_devices(),
+        })
+    }
+
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        with_backend(|backend| match backend {
+            Backend::PulseAudio => pulseaudio::get_sound_device_uids(),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => pipewire::get_sound_device_uids(),
+            #[cfg(feature = "jack")]
+            Backend::Jack => jack::get_sound_device_uids(),
+            Backend::Alsa => alsa::get_sound_device_uids(),
+        })
+    }
+
+    pub fn get_vol() -> Result<f32, Error> {
+        get_default_output_dev()?.get_vol()
+    }
+
+    pub fn set_vol(value: f32) -> Result<(), Error> {
+        get_default_output_dev()?.set_vol(value)
+    }
+
+    pub fn get_mute() -> Result<bool, Error> {
+        get_default_output_dev()?.get_mute()
+    }
+
+    pub fn set_mute(state: bool) -> Result<(), Error> {
+        get_default_output_dev()?.set_mute(state)
+    }
+
+    // Input Controls
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        with_backend(|backend| match backend {
+            Backend::PulseAudio => pulseaudio::get_input_devices(),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => pipewire::get_input_devices(),
+            #[cfg(feature = "jack")]
+            Backend::Jack => jack::get_input_devices(),
+            Backend::Alsa => alsa::get_input_devices(),
+        })
+    }
+
+    pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+        with_backend(|backend| match backend {
+            Backend::PulseAudio => pulseaudio::get_input_device_uids(),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => pipewire::get_input_device_uids(),
+            #[cfg(feature = "jack")]
+            Backend::Jack => jack::get_input_device_uids(),
+            Backend::Alsa => alsa::get_input_device_uids(),
+        })
+    }
+
+    pub fn get_input_vol() -> Result<f32, Error> {
+        get_default_input_dev()?.get_vol()
+    }
+
+    pub fn set_input_vol(value: f32) -> Result<(), Error> {
+        get_default_input_dev()?.set_vol(value)
+    }
+
+    pub fn get_input_mute() -> Result<bool, Error> {
+        get_default_input_dev()?.get_mute()
+    }
+
+    pub fn set_input_mute(state: bool) -> Result<(), Error> {
+        get_default_input_dev()?.set_mute(state)
+    }
+
+    // Change notifications need a sound server
+    pub fn subscribe(sender: Sender<Event>) -> Result<Subscription, Error> {
+        with_backend(|backend| match backend {
+            Backend::PulseAudio => pulseaudio::subscribe::subscribe(sender),
+            #[cfg(feature = "pipewire")]
+            Backend::PipeWire => Err(Error::PlatformUnsupported),
+            #[cfg(feature = "jack")]
+            Backend::Jack => Err(Error::PlatformUnsupported),
+            Backend::Alsa => Err(Error::PlatformUnsupported),
+        })
+    }
+
+}
+
+#[cfg(not(target_os="linux"))]
+// #[cfg(target_os="linux")]
+mod linux {
+
+}
+
+pub use linux::*;
@@ -0,0 +1,995 @@
+use std::{cell::RefCell, collections::HashMap, sync::{Arc, Mutex, mpsc::Sender}};
+use crate::{DeviceType, curve::{self, VolumeCurve}, device::{self, ChannelVolume, DeviceInfo, DeviceOpener, DeviceTrait, Port, PortAvailability}, error::Error, event::{Event, Subscription}, pulseaudio::options::ClientIdentity};
+
+// In-memory stand-in for a sound server, selected with the `mock` feature
+//
+// Every thread talks to its own `MockSystem` so tests running in parallel do not share devices,
+// devices created on one thread keep using the system they were found in
+
+thread_local! {
+    static CURRENT_SYSTEM: RefCell<Option<MockSystem>> = const { RefCell::new(None) };
+}
+
+struct MockDeviceState {
+    uid: String,
+    name: String,
+    device_type: DeviceType,
+    channels: Vec<ChannelVolume>,
+    mute: bool,
+    ports: Vec<Port>,
+    active_port: Option<String>,
+    db_volume: bool,
+    mute_at_zero: bool,
+    info: DeviceInfo,
+}
+
+#[derive(Default)]
+struct MockState {
+    devices: Vec<MockDeviceState>,
+    default_output: Option<String>,
+    default_input: Option<String>,
+    next_error: Option<Error>,
+    device_errors: HashMap<String, Error>,
+    subscribers: Vec<(u64, Sender<Event>)>,
+    next_subscriber: u64,
+    identity: ClientIdentity,
+}
+
+impl MockState {
+    // Injected errors win over the result of the operation
+    fn check(&mut self, uid: Option<&str>) -> Result<(), Error> {
+        if let Some(error) = self.next_error.take() {
+            return Err(error);
+        }
+        if let Some(uid) = uid && let Some(error) = self.device_errors.get(uid) {
+            return Err(error.clone());
+        }
+        Ok(())
+    }
+
+    fn device(&mut self, uid: &str) -> Result<&mut MockDeviceState, Error> {
+        self.check(Some(uid))?;
+        self.find(uid)
+    }
+
+    // Scripting the system bypasses injected errors
+    fn find(&mut self, uid: &str) -> Result<&mut MockDeviceState, Error> {
+        self.devices.iter_mut()
+            .find(|device| device.uid == uid)
+            .ok_or(Error::DeviceNotFound)
+    }
+
+    fn default_uid(&self, device_type: DeviceType) -> Option<String> {
+        match device_type {
+            DeviceType::Input => self.default_input.clone(),
+            _ => self.default_output.clone(),
+        }
+    }
+
+    fn set_default_uid(&mut self, device_type: DeviceType, uid: Option<String>, origin: Option<ClientIdentity>) {
+        match device_type {
+            DeviceType::Input => self.default_input = uid.clone(),
+            _ => self.default_output = uid.clone(),
+        }
+        if let Some(uid) = uid {
+            self.emit(Event::DefaultDeviceChanged { uid, device_type, origin });
+        }
+    }
+
+    fn emit(&mut self, event: Event) {
+        self.subscribers.retain(|(_, sender)| sender.send(event.clone()).is_ok());
+    }
+}
+
+/// Scriptable in-memory sound system backing `MockDevice`
+///
+/// Cloned handles share the same devices
+#[derive(Clone, Default)]
+pub struct MockSystem {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockSystem {
+    /// Creates an empty system and makes it the current system of the calling thread
+    pub fn new() -> Self {
+        let system = MockSystem::default();
+        system.install();
+        system
+    }
+
+    /// System used by `Device::from_uid` and the crate functions on the calling thread
+    ///
+    /// An empty system is created if the thread has none
+    pub fn current() -> Self {
+        CURRENT_SYSTEM.with(|current| {
+            current.borrow_mut().get_or_insert_with(MockSystem::default).clone()
+        })
+    }
+
+    /// Makes this system the current system of the calling thread
+    pub fn install(&self) {
+        CURRENT_SYSTEM.with(|current| {
+            current.borrow_mut().replace(self.clone());
+        });
+    }
+
+    /// Identity carried as `origin` by the events of changes made through `MockDevice` and the crate functions
+    ///
+    /// `cpvc::set_connection_options` sets it on the current system, `ClientIdentity::default()` until then
+    pub fn set_identity(&self, identity: ClientIdentity) {
+        self.state.lock().unwrap().identity = identity;
+    }
+
+    pub fn get_identity(&self) -> ClientIdentity {
+        self.state.lock().unwrap().identity.clone()
+    }
+
+    // Changes made through `MockDevice` come from this process, like the requests of a real backend
+    fn local_origin(&self) -> Option<ClientIdentity> {
+        Some(self.get_identity())
+    }
+
+    /// Adds a stereo device at full volume, the first device of each direction becomes the default
+    pub fn add_device(&self, uid: &str, name: &str, device_type: DeviceType) {
+        let mut state = self.state.lock().unwrap();
+        state.devices.push(MockDeviceState {
+            uid: uid.to_string(),
+            name: name.to_string(),
+            device_type,
+            channels: vec![
+                ChannelVolume { position: String::from("front-left"), volume: 1.0 },
+                ChannelVolume { position: String::from("front-right"), volume: 1.0 },
+            ],
+            mute: false,
+            ports: Vec::new(),
+            active_port: None,
+            db_volume: false,
+            mute_at_zero: false,
+            info: DeviceInfo::default(),
+        });
+        state.emit(Event::DeviceAdded { uid: uid.to_string(), device_type });
+        if state.default_uid(device_type).is_none() {
+            state.set_default_uid(device_type, Some(uid.to_string()), None);
+        }
+    }
+
+    /// Removes a device, the next device of the same direction takes over as default
+    pub fn remove_device(&self, uid: &str) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let position = state.devices.iter()
+            .position(|device| device.uid == uid)
+            .ok_or(Error::DeviceNotFound)?;
+        let device = state.devices.remove(position);
+        state.emit(Event::DeviceRemoved { uid: device.uid.clone(), device_type: device.device_type });
+        if state.default_uid(device.device_type).as_deref() == Some(uid) {
+            let next = state.devices.iter()
+                .find(|other| other.device_type == device.device_type)
+                .map(|other| other.uid.clone());
+            state.set_default_uid(device.device_type, next, None);
+        }
+        Ok(())
+    }
+
+    /// Changes the default device the way another client would, events carry no origin
+    pub fn set_default(&self, uid: &str) -> Result<(), Error> {
+        self.set_default_from(uid, None)
+    }
+
+    fn set_default_from(&self, uid: &str, origin: Option<ClientIdentity>) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let device_type = state.find(uid)?.device_type;
+        state.set_default_uid(device_type, Some(uid.to_string()), origin);
+        Ok(())
+    }
+
+    /// Replaces the channel layout of a device (e.g. to test balance on surround devices)
+    pub fn set_channels(&self, uid: &str, channels: Vec<ChannelVolume>) -> Result<(), Error> {
+        self.state.lock().unwrap().find(uid)?.channels = channels;
+        Ok(())
+    }
+
+    /// Sets what `has_db_volume` reports for the device, dB values are computed the same way either way
+    pub fn set_db_volume(&self, uid: &str, db_volume: bool) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.find(uid)?.db_volume = db_volume;
+        Ok(())
+    }
+
+    /// Makes `set_vol(0.0)` mute the device and keep its channel volumes, the way PulseAudio does
+    pub fn set_mute_at_zero(&self, uid: &str, mute_at_zero: bool) -> Result<(), Error> {
+        self.state.lock().unwrap().find(uid)?.mute_at_zero = mute_at_zero;
+        Ok(())
+    }
+
+    /// Sets the metadata `get_info` reports, channels and the dB flag always come from the device itself
+    pub fn set_info(&self, uid: &str, info: DeviceInfo) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.find(uid)?.info = info;
+        Ok(())
+    }
+
+    /// Adds a port to a device, the first port becomes the active one
+    pub fn add_port(&self, uid: &str, port: Port) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let device = state.find(uid)?;
+        if device.active_port.is_none() {
+            device.active_port = Some(port.name.clone());
+        }
+        device.ports.push(port);
+        Ok(())
+    }
+
+    /// Simulates plugging or unplugging a jack
+    pub fn set_port_availability(&self, uid: &str, port: &str, availability: PortAvailability) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let device = state.find(uid)?;
+        let entry = device.ports.iter_mut()
+            .find(|entry| entry.name == port)
+            .ok_or(Error::DeviceAccessFailed(format!("{} has no port {}", uid, port)))?;
+        if entry.availability != availability {
+            entry.availability = availability;
+            state.emit(Event::PortAvailabilityChanged { uid: uid.to_string(), port: port.to_string(), availability });
+        }
+        Ok(())
+    }
+
+    /// Fails the next operation on any device with `error`
+    pub fn fail_next(&self, error: Error) {
+        self.state.lock().unwrap().next_error = Some(error);
+    }
+
+    /// Fails every operation on `uid` with `error` until `clear_errors` is called
+    pub fn fail_device(&self, uid: &str, error: Error) {
+        self.state.lock().unwrap().device_errors.insert(uid.to_string(), error);
+    }
+
+    pub fn clear_errors(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.next_error = None;
+        state.device_errors.clear();
+    }
+
+    pub fn get_devices(&self, device_type: DeviceType) -> Result<Vec<MockDevice>, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check(None)?;
+        Ok(state.devices.iter()
+            .filter(|device| device.device_type == device_type)
+            .map(|device| MockDevice { uid: device.uid.clone(), system: self.clone() })
+            .collect())
+    }
+
+    pub fn get_default_device(&self, device_type: DeviceType) -> Result<MockDevice, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check(None)?;
+        let uid = state.default_uid(device_type).ok_or(Error::DeviceNotFound)?;
+        Ok(MockDevice { uid, system: self.clone() })
+    }
+
+    /// Delivers every change made to the system to `sender`
+    pub fn subscribe(&self, sender: Sender<Event>) -> Result<Subscription, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check(None)?;
+        let id = state.next_subscriber;
+        state.next_subscriber += 1;
+        state.subscribers.push((id, sender));
+
+        let system = self.clone();
+        Ok(Subscription::new(move || {
+            system.state.lock().unwrap().subscribers.retain(|(subscriber, _)| *subscriber != id);
+        }))
+    }
+
+    fn with_device<R>(&self, uid: &str, action: impl FnOnce(&mut MockDeviceState) -> Result<R, Error>) -> Result<R, Error> {
+        action(self.state.lock().unwrap().device(uid)?)
+    }
+
+    fn emit(&self, event: Event) {
+        self.state.lock().unwrap().emit(event);
+    }
+}
+
+/// Device living in a `MockSystem`
+pub struct MockDevice {
+    uid: String,
+    system: MockSystem,
+}
+
+impl MockDevice {
+    pub fn get_system(&self) -> MockSystem {
+        self.system.clone()
+    }
+
+    fn set_channels(&self, channels: Vec<ChannelVolume>) -> Result<(), Error> {
+        let volume = self.with_device(|device| {
+            device.channels = channels;
+            Ok(if device.mute { 0.0 } else { max_volume(&device.channels) })
+        })?;
+        // Reported like `Device::get_vol` of a device without its own curve
+        let volume = VolumeCurve::Cubic.convert(volume, curve::get_volume_curve());
+        self.system.emit(Event::VolumeChanged { uid: self.uid.clone(), volume, origin: self.system.local_origin() });
+        Ok(())
+    }
+
+    fn with_device<R>(&self, action: impl FnOnce(&mut MockDeviceState) -> Result<R, Error>) -> Result<R, Error> {
+        self.system.with_device(&self.uid, action)
+    }
+}
+
+fn max_volume(channels: &[ChannelVolume]) -> f32 {
+    channels.iter().map(|channel| channel.volume).fold(0.0, f32::max)
+}
+
+// Same cubic mapping as PulseAudio, 1.0 is 0 dB
+fn to_db(volume: f32) -> f32 {
+    60.0 * volume.log10()
+}
+
+fn from_db(db: f32) -> f32 {
+    10f32.powf(db / 60.0)
+}
+
+// Same balance math as PulseAudio, `first` and `second` select the two sides (e.g. "left" and "right")
+fn get_side_balance(channels: &[ChannelVolume], first: &str, second: &str) -> Option<f32> {
+    let side = |name: &str| channels.iter()
+        .filter(|channel| channel.position.contains(name))
+        .map(|channel| channel.volume)
+        .fold(None, |max: Option<f32>, volume| Some(max.map_or(volume, |max| max.max(volume))));
+    let (first, second) = (side(first)?, side(second)?);
+    if first == second {
+        Some(0.0)
+    } else if first > second {
+        Some(-1.0 + second / first)
+    } else {
+        Some(1.0 - first / second)
+    }
+}
+
+fn set_side_balance(channels: &mut [ChannelVolume], first: &str, second: &str, balance: f32) -> Result<(), Error> {
+    get_side_balance(channels, first, second)
+        .ok_or(Error::VolumeSetFailed(format!("Device has no {}/{} channels", first, second)))?;
+    for channel in channels.iter_mut() {
+        let scale = if channel.position.contains(first) {
+            if balance > 0.0 { 1.0 - balance } else { 1.0 }
+        } else if channel.position.contains(second) {
+            if balance < 0.0 { 1.0 + balance } else { 1.0 }
+        } else {
+            continue;
+        };
+        channel.volume *= scale;
+    }
+    Ok(())
+}
+
+impl DeviceTrait for MockDevice {
+    fn from_name(name: String) -> Result<Self, Error> {
+        let system = MockSystem::current();
+        let mut state = system.state.lock().unwrap();
+        state.check(None)?;
+        let uid = state.devices.iter()
+            .find(|device| device.name == name)
+            .map(|device| device.uid.clone())
+            .ok_or(Error::DeviceNotFound)?;
+        drop(state);
+        Ok(MockDevice { uid, system })
+    }
+
+    fn from_uid(uid: String) -> Result<Self, Error> {
+        let system = MockSystem::current();
+        system.with_device(&uid, |_| Ok(()))?;
+        Ok(MockDevice { uid, system })
+    }
+
+    fn get_name(&self) -> Result<String, Error> {
+        self.with_device(|device| Ok(device.name.clone()))
+    }
+
+    fn get_uid(&self) -> Result<String, Error> {
+        Ok(self.uid.clone())
+    }
+
+    fn get_device_type(&self) -> Result<DeviceType, Error> {
+        self.with_device(|device| Ok(device.device_type))
+    }
+
+    fn get_vol(&self) -> Result<f32, Error> {
+        self.with_device(|device| Ok(if device.mute { 0.0 } else { max_volume(&device.channels) }))
+    }
+
+    // Scales every channel so the loudest one matches `value`
+    fn set_vol(&self, value: f32) -> Result<(), Error> {
+        device::check_vol(&self.uid, value)?;
+        if value == 0.0 && self.with_device(|device| Ok(device.mute_at_zero))? {
+            return self.set_mute(true);
+        }
+        let mut channels = self.with_device(|device| Ok(device.channels.clone()))?;
+        let max = max_volume(&channels);
+        for channel in channels.iter_mut() {
+            channel.volume = if max == 0.0 { value } else { channel.volume / max * value };
+        }
+        self.set_channels(channels)
+    }
+
+    fn get_mute(&self) -> Result<bool, Error> {
+        self.with_device(|device| Ok(device.mute))
+    }
+
+    fn set_mute(&self, mute: bool) -> Result<(), Error> {
+        let changed = self.with_device(|device| {
+            let changed = device.mute != mute;
+            device.mute = mute;
+            Ok(changed)
+        })?;
+        if changed {
+            self.system.emit(Event::MuteChanged { uid: self.uid.clone(), mute, origin: self.system.local_origin() });
+        }
+        Ok(())
+    }
+
+    fn set_default(&self, _move_streams: bool) -> Result<(), Error> {
+        self.with_device(|_| Ok(()))?;
+        self.system.set_default_from(&self.uid, self.system.local_origin())
+    }
+
+    fn get_ports(&self) -> Result<Vec<Port>, Error> {
+        self.with_device(|device| Ok(device.ports.clone()))
+    }
+
+    fn get_active_port(&self) -> Result<Option<Port>, Error> {
+        self.with_device(|device| {
+            Ok(device.ports.iter()
+                .find(|port| Some(&port.name) == device.active_port.as_ref())
+                .cloned())
+        })
+    }
+
+    fn set_active_port(&self, name: String) -> Result<(), Error> {
+        let uid = self.uid.clone();
+        self.with_device(|device| {
+            if !device.ports.iter().any(|port| port.name == name) {
+                return Err(Error::DeviceAccessFailed(format!("{} has no port {}", uid, name)));
+            }
+            device.active_port = Some(name.clone());
+            Ok(())
+        })?;
+        self.system.emit(Event::ActivePortChanged { uid: self.uid.clone(), port: name, origin: self.system.local_origin() });
+        Ok(())
+    }
+
+    fn get_channel_vols(&self) -> Result<Vec<ChannelVolume>, Error> {
+        self.with_device(|device| Ok(device.channels.clone()))
+    }
+
+    fn set_channel_vol(&self, channel: usize, value: f32) -> Result<(), Error> {
+        device::check_vol(&self.uid, value)?;
+        let mut channels = self.with_device(|device| Ok(device.channels.clone()))?;
+        let count = channels.len();
+        match channels.get_mut(channel) {
+            Some(entry) => {
+                entry.volume = value;
+            },
+            None => {
+                return Err(Error::VolumeSetFailed(format!("Channel {} out of range, device has {} channels", channel, count)));
+            }
+        }
+        self.set_channels(channels)
+    }
+
+    fn get_balance(&self) -> Result<f32, Error> {
+        self.with_device(|device| Ok(get_side_balance(&device.channels, "left", "right").unwrap_or(0.0)))
+    }
+
+    fn set_balance(&self, balance: f32) -> Result<(), Error> {
+        if !(-1.0..=1.0).contains(&balance) {
+            return Err(Error::VolumeSetFailed(format!("Balance {} outside of -1.0 to 1.0", balance)));
+        }
+        let mut channels = self.with_device(|device| Ok(device.channels.clone()))?;
+        let max = max_volume(&channels);
+        for channel in channels.iter_mut() {
+            channel.volume = max;
+        }
+        set_side_balance(&mut channels, "left", "right", balance)?;
+        self.set_channels(channels)
+    }
+
+    fn get_fade(&self) -> Result<f32, Error> {
+        self.with_device(|device| Ok(-get_side_balance(&device.channels, "front", "rear").unwrap_or(0.0)))
+    }
+
+    fn set_fade(&self, fade: f32) -> Result<(), Error> {
+        if !(-1.0..=1.0).contains(&fade) {
+            return Err(Error::VolumeSetFailed(format!("Fade {} outside of -1.0 to 1.0", fade)));
+        }
+        let mut channels = self.with_device(|device| Ok(device.channels.clone()))?;
+        let max = max_volume(&channels);
+        for channel in channels.iter_mut() {
+            channel.volume = max;
+        }
+        set_side_balance(&mut channels, "front", "rear", -fade)?;
+        self.set_channels(channels)
+    }
+
+    fn get_vol_db(&self) -> Result<f32, Error> {
+        self.with_device(|device| Ok(if device.mute { f32::NEG_INFINITY } else { to_db(max_volume(&device.channels)) }))
+    }
+
+    fn set_vol_db(&self, db: f32) -> Result<(), Error> {
+        if db.is_nan() {
+            return Err(Error::VolumeSetFailed(format!("Volume {} dB is not a number", db)));
+        }
+        self.set_vol(from_db(db))
+    }
+
+    fn has_db_volume(&self) -> Result<bool, Error> {
+        self.with_device(|device| Ok(device.db_volume))
+    }
+
+    fn supports_boost(&self) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    // Matches the dB conversion of `get_vol_db`
+    fn get_native_curve(&self) -> Result<VolumeCurve, Error> {
+        Ok(VolumeCurve::Cubic)
+    }
+
+    fn get_info(&self) -> Result<DeviceInfo, Error> {
+        self.with_device(|device| Ok(DeviceInfo {
+            channels: Some(device.channels.len() as u8),
+            channel_map: Some(device.channels.iter().map(|channel| channel.position.clone()).collect()),
+            db_volume: Some(device.db_volume),
+            ..device.info.clone()
+        }))
+    }
+
+    // Systems are per thread, the opened device keeps pointing to this one
+    fn get_opener(&self) -> Result<DeviceOpener<Self>, Error> {
+        let device = MockDevice { uid: self.uid.clone(), system: self.system.clone() };
+        Ok(Box::new(move || Ok(device)))
+    }
+}
+
+// Counterparts of the platform module functions, used by the crate functions when `mock` is enabled
+
+pub fn get_sound_devices() -> Result<Vec<String>, Error> {
+    MockSystem::current().get_devices(DeviceType::Output)?.iter().map(|device| device.get_name()).collect()
+}
+
+pub fn get_input_devices() -> Result<Vec<String>, Error> {
+    MockSystem::current().get_devices(DeviceType::Input)?.iter().map(|device| device.get_name()).collect()
+}
+
+pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+    MockSystem::current().get_devices(DeviceType::Output)?.iter().map(|device| device.get_uid()).collect()
+}
+
+pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+    MockSystem::current().get_devices(DeviceType::Input)?.iter().map(|device| device.get_uid()).collect()
+}
+
+pub fn get_vol() -> Result<f32, Error> {
+    get_default_output_dev()?.get_vol()
+}
+
+pub fn set_vol(value: f32) -> Result<(), Error> {
+    get_default_output_dev()?.set_vol(value)
+}
+
+pub fn get_mute() -> Result<bool, Error> {
+    get_default_output_dev()?.get_mute()
+}
+
+pub fn set_mute(state: bool) -> Result<(), Error> {
+    get_default_output_dev()?.set_mute(state)
+}
+
+pub fn get_input_vol() -> Result<f32, Error> {
+    get_default_input_dev()?.get_vol()
+}
+
+pub fn set_input_vol(value: f32) -> Result<(), Error> {
+    get_default_input_dev()?.set_vol(value)
+}
+
+pub fn get_input_mute() -> Result<bool, Error> {
+    get_default_input_dev()?.get_mute()
+}
+
+pub fn set_input_mute(state: bool) -> Result<(), Error> {
+    get_default_input_dev()?.set_mute(state)
+}
+
+pub fn get_default_output_dev() -> Result<MockDevice, Error> {
+    MockSystem::current().get_default_device(DeviceType::Output)
+}
+
+pub fn get_default_input_dev() -> Result<MockDevice, Error> {
+    MockSystem::current().get_default_device(DeviceType::Input)
+}
+
+pub fn subscribe(sender: Sender<Event>) -> Result<Subscription, Error> {
+    MockSystem::current().subscribe(sender)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::{sync::mpsc, time::Duration};
+    use crate::{DeviceType, curve::VolumeCurve, device::{Device, DeviceDescriptor, DeviceInfo, Port, PortAvailability, PortType}, error::Error, event::Event, fade::FadeStatus, pulseaudio::options::{ClientIdentity, ConnectionOptions}};
+    use super::MockSystem;
+
+    fn speakers_and_headset() -> MockSystem {
+        let system = MockSystem::new();
+        system.add_device("speakers", "Speakers", DeviceType::Output);
+        system.add_device("headset", "Headset", DeviceType::Output);
+        system.add_device("mic", "Microphone", DeviceType::Input);
+        system
+    }
+
+    #[test]
+    fn test_mock_volume() {
+        let _system = speakers_and_headset();
+        assert_eq!(crate::get_sound_devices(), vec!["Speakers", "Headset"]);
+        assert!(crate::set_system_volume(0.25));
+        assert_eq!(crate::get_system_volume(), 0.25);
+        assert!(crate::set_mute(true));
+        assert!(crate::get_mute());
+        assert_eq!(crate::get_system_volume(), 0.0);
+        assert_eq!(crate::try_set_system_volume(1.5), Err(Error::VolumeSetFailed(format!("Volume 1.5 above 1.0, boosting is not allowed on speakers"))));
+    }
+
+    #[test]
+    fn test_mock_db_volume() {
+        let system = speakers_and_headset();
+        let device = crate::get_default_output_device().unwrap();
+        assert_eq!(device.get_vol_db(), Ok(0.0));
+        device.set_vol_db(-6.0).unwrap();
+        assert!((device.get_vol_db().unwrap() + 6.0).abs() < 0.001);
+        assert!((device.get_vol().unwrap() - 0.7943).abs() < 0.001);
+        assert!(matches!(device.set_vol_db(3.0), Err(Error::VolumeSetFailed(_))));
+        device.set_mute(true).unwrap();
+        assert_eq!(device.get_vol_db(), Ok(f32::NEG_INFINITY));
+        assert_eq!(device.has_db_volume(), Ok(false));
+        system.set_db_volume("speakers", true).unwrap();
+        assert_eq!(device.has_db_volume(), Ok(true));
+    }
+
+    #[test]
+    fn test_mock_boost() {
+        let system = MockSystem::new();
+        // Boost settings are global, a UID no other test uses keeps them isolated
+        system.add_device("amplified", "Amplified", DeviceType::Output);
+        let device = crate::get_default_output_device().unwrap();
+        assert_eq!(device.is_boost_allowed(), Ok(false));
+        assert_eq!(device.get_max_vol(), Ok(1.0));
+        assert!(device.set_vol(1.2).is_err());
+        device.set_boost_allowed(true).unwrap();
+        assert_eq!(device.get_max_vol(), Ok(crate::device::DEFAULT_MAX_VOL));
+        device.set_vol(1.5).unwrap();
+        assert_eq!(device.get_vol(), Ok(1.5));
+        assert_eq!(device.set_vol(1.6), Err(Error::VolumeSetFailed(format!("Volume 1.6 above the {} ceiling of amplified", crate::device::DEFAULT_MAX_VOL))));
+        device.set_max_vol(2.0).unwrap();
+        device.set_vol(1.6).unwrap();
+        assert!(device.set_max_vol(0.5).is_err());
+        assert_eq!(device.set_vol(-0.1), Err(Error::VolumeSetFailed(format!("Volume -0.1 outside of 0.0 to 2"))));
+        device.set_boost_allowed(false).unwrap();
+        assert_eq!(device.get_max_vol(), Ok(1.0));
+    }
+
+    #[test]
+    fn test_mock_volume_curve() {
+        let _system = speakers_and_headset();
+        let mut device = crate::get_default_output_device().unwrap();
+        assert_eq!(device.get_native_curve(), Ok(VolumeCurve::Cubic));
+        device.set_curve(Some(VolumeCurve::Linear)).unwrap();
+        device.set_vol(0.125).unwrap();
+        assert!((device.get_vol().unwrap() - 0.125).abs() < 0.0001);
+        device.set_curve(None).unwrap();
+        assert!((device.get_vol().unwrap() - 0.5).abs() < 0.0001);
+        device.set_curve(Some(VolumeCurve::Logarithmic { range: 60.0 })).unwrap();
+        device.set_vol(0.5).unwrap();
+        assert!((device.get_vol_db().unwrap() + 30.0).abs() < 0.01);
+        assert!((device.get_channel_vols().unwrap()[0].volume - 0.5).abs() < 0.0001);
+        assert!(device.set_curve(Some(VolumeCurve::Logarithmic { range: 0.0 })).is_err());
+    }
+
+    #[test]
+    fn test_mock_fade() {
+        let system = MockSystem::new();
+        // Running fades are tracked per UID across threads, so the UID is unique to this test
+        system.add_device("fader", "Fader", DeviceType::Output);
+        let device = crate::get_default_output_device().unwrap();
+        device.set_vol(0.0).unwrap();
+        let fade = device.fade_to(0.6, Duration::from_millis(100), VolumeCurve::Linear).unwrap();
+        assert_eq!(fade.wait(), Ok(FadeStatus::Completed));
+        assert_eq!(device.get_vol(), Ok(0.6));
+
+        let slow = device.fade_to(0.0, Duration::from_secs(10), VolumeCurve::Cubic).unwrap();
+        let fast = device.fade_to(1.0, Duration::from_millis(50), VolumeCurve::Cubic).unwrap();
+        assert_eq!(slow.wait(), Ok(FadeStatus::Superseded));
+        assert_eq!(fast.wait(), Ok(FadeStatus::Completed));
+        assert_eq!(device.get_vol(), Ok(1.0));
+
+        let fade = device.fade_to(0.0, Duration::from_secs(10), VolumeCurve::Cubic).unwrap();
+        fade.cancel();
+        assert_eq!(fade.wait(), Ok(FadeStatus::Cancelled));
+        assert!(device.get_vol().unwrap() > 0.5);
+        assert!(matches!(device.fade_to(2.0, Duration::ZERO, VolumeCurve::Cubic), Err(Error::VolumeSetFailed(_))));
+        assert!(matches!(device.fade_to(f32::NAN, Duration::ZERO, VolumeCurve::Cubic), Err(Error::VolumeSetFailed(_))));
+    }
+
+    #[test]
+    fn test_mock_fade_from_zero() {
+        let system = MockSystem::new();
+        system.add_device("zero_fader", "Zero Fader", DeviceType::Output);
+        let mut device = crate::get_default_output_device().unwrap();
+        device.set_vol(0.0).unwrap();
+        system.set_mute_at_zero("zero_fader", true).unwrap();
+        // Steps below -20 dB are 0.0 on this curve, so the first fifth of the fade mutes the device
+        device.set_curve(Some(VolumeCurve::Logarithmic { range: 20.0 })).unwrap();
+        let fade = device.fade_to(0.5, Duration::from_millis(500), VolumeCurve::Linear).unwrap();
+        assert_eq!(fade.wait(), Ok(FadeStatus::Completed));
+        assert_eq!(device.get_mute(), Ok(false));
+        assert!((device.get_vol().unwrap() - 0.5).abs() < 0.001);
+
+        device.fade_to(0.0, Duration::from_millis(50), VolumeCurve::Linear).unwrap().wait().unwrap();
+        assert_eq!(device.get_mute(), Ok(true));
+    }
+
+    #[test]
+    fn test_mock_system_state() {
+        let _system = speakers_and_headset();
+        crate::set_system_volume(0.5);
+        let state = crate::system_state().unwrap();
+        assert_eq!(state.outputs.len(), 2);
+        assert_eq!(state.outputs[0], DeviceDescriptor {
+            uid: format!("speakers"),
+            name: format!("Speakers"),
+            device_type: DeviceType::Output,
+            volume: 0.5,
+            mute: false,
+            default: true,
+        });
+        assert!(!state.outputs[1].default);
+        assert_eq!(state.inputs.len(), 1);
+        assert!(state.inputs[0].default);
+    }
+
+    #[test]
+    fn test_mock_system_state_shared_name() {
+        let system = MockSystem::new();
+        system.add_device("usb_out", "USB Audio", DeviceType::Output);
+        system.add_device("usb_in", "USB Audio", DeviceType::Input);
+        system.add_device("usb_out_2", "USB Audio", DeviceType::Output);
+        let state = crate::system_state().unwrap();
+        let uids = |devices: &[DeviceDescriptor]| devices.iter().map(|device| device.uid.clone()).collect::<Vec<_>>();
+        assert_eq!(uids(&state.outputs), vec![format!("usb_out"), format!("usb_out_2")]);
+        assert_eq!(uids(&state.inputs), vec![format!("usb_in")]);
+        assert_eq!(state.inputs[0].device_type, DeviceType::Input);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_mock_serde() {
+        let _system = speakers_and_headset();
+        let json = serde_json::to_value(crate::system_state().unwrap()).unwrap();
+        assert_eq!(json["outputs"][1]["name"], "Headset");
+        assert_eq!(json["inputs"][0]["device_type"], "Input");
+        let error = serde_json::to_string(&Error::VolumeSetFailed(format!("Volume 2 outside of 0.0 to 1.0"))).unwrap();
+        assert_eq!(serde_json::from_str::<Error>(&error).unwrap(), Error::VolumeSetFailed(format!("Volume 2 outside of 0.0 to 1.0")));
+        let snapshot = crate::snapshot().unwrap();
+        let saved = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<crate::snapshot::Snapshot>(&saved).unwrap(), snapshot);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_mock_async() {
+        use futures_executor::{block_on, block_on_stream};
+        use crate::asynchronous::{self, AsyncDevice};
+        let system = speakers_and_headset();
+        system.set_identity(ClientIdentity::new("Async Mixer"));
+        let mut events = block_on_stream(block_on(asynchronous::subscribe()).unwrap());
+
+        block_on(asynchronous::set_system_volume(0.3)).unwrap();
+        assert_eq!(block_on(asynchronous::get_system_volume()), Ok(0.3));
+        let origin = Some(ClientIdentity::new("Async Mixer"));
+        assert_eq!(events.next(), Some(Event::VolumeChanged { uid: String::from("speakers"), volume: 0.3, origin: origin.clone() }));
+
+        let headset = block_on(AsyncDevice::from_uid(format!("headset"))).unwrap();
+        block_on(headset.set_mute(true)).unwrap();
+        assert_eq!(block_on(headset.get_name()), Ok(format!("Headset")));
+        assert_eq!(events.next(), Some(Event::MuteChanged { uid: String::from("headset"), mute: true, origin }));
+        assert_eq!(Device::from_uid(format!("headset")).unwrap().get_mute(), Ok(true));
+
+        let mut speakers = Device::from_uid(format!("speakers")).unwrap();
+        speakers.set_curve(Some(VolumeCurve::Linear)).unwrap();
+        let speakers = block_on(AsyncDevice::from_device(&speakers)).unwrap();
+        assert_eq!(block_on(speakers.get_curve()), Ok(VolumeCurve::Linear));
+        assert!(matches!(block_on(speakers.set_vol(1.5)), Err(Error::VolumeSetFailed(_))));
+        assert!(matches!(block_on(AsyncDevice::from_uid(format!("missing"))), Err(Error::DeviceNotFound)));
+    }
+
+    #[test]
+    fn test_mock_snapshot() {
+        let system = speakers_and_headset();
+        for name in ["speakers", "line-out"] {
+            system.add_port("speakers", Port {
+                name: String::from(name),
+                description: String::from(name),
+                priority: 100,
+                availability: PortAvailability::Unknown,
+                port_type: PortType::Speaker,
+            }).unwrap();
+        }
+        let speakers = Device::from_uid(format!("speakers")).unwrap();
+        let headset = Device::from_uid(format!("headset")).unwrap();
+        speakers.set_channel_vol(1, 0.4).unwrap();
+        headset.set_mute(true).unwrap();
+        headset.make_default(false).unwrap();
+        let snapshot = crate::snapshot().unwrap();
+        assert_eq!(snapshot.devices.len(), 3);
+        assert_eq!(snapshot.default_output.as_deref(), Some("headset"));
+        assert!(snapshot.streams.is_empty());
+
+        speakers.set_active_port(format!("line-out")).unwrap();
+        speakers.set_vol(0.9).unwrap();
+        speakers.make_default(false).unwrap();
+        headset.set_mute(false).unwrap();
+        assert!(crate::restore(&snapshot).unwrap().is_complete());
+        assert_eq!(speakers.get_active_port().unwrap().map(|port| port.name), Some(format!("speakers")));
+        assert_eq!(speakers.get_channel_vols().unwrap().iter().map(|channel| channel.volume).collect::<Vec<_>>(), vec![1.0, 0.4]);
+        assert_eq!(headset.get_mute(), Ok(true));
+        assert_eq!(crate::get_default_output_device().unwrap().get_uid(), Ok(format!("headset")));
+
+        system.remove_device("headset").unwrap();
+        let report = crate::restore(&snapshot).unwrap();
+        assert_eq!(report.missing_devices, vec![format!("headset")]);
+        assert!(report.failed.is_empty());
+    }
+
+    #[test]
+    fn test_mock_snapshot_shared_name() {
+        let system = MockSystem::new();
+        system.add_device("usb_out", "USB Audio", DeviceType::Output);
+        system.add_device("usb_in", "USB Audio", DeviceType::Input);
+        let output = Device::from_uid(format!("usb_out")).unwrap();
+        let input = Device::from_uid(format!("usb_in")).unwrap();
+        output.set_vol(0.3).unwrap();
+        input.set_vol(0.7).unwrap();
+        input.set_mute(true).unwrap();
+        let snapshot = crate::snapshot().unwrap();
+        let uids = snapshot.devices.iter().map(|device| (device.uid.as_str(), device.device_type)).collect::<Vec<_>>();
+        assert_eq!(uids, vec![("usb_out", DeviceType::Output), ("usb_in", DeviceType::Input)]);
+
+        output.set_vol(1.0).unwrap();
+        input.set_vol(1.0).unwrap();
+        input.set_mute(false).unwrap();
+        assert!(crate::restore(&snapshot).unwrap().is_complete());
+        assert!((output.get_vol().unwrap() - 0.3).abs() < 0.001);
+        assert_eq!(output.get_mute(), Ok(false));
+        assert_eq!(input.get_mute(), Ok(true));
+        input.set_mute(false).unwrap();
+        assert!((input.get_vol().unwrap() - 0.7).abs() < 0.001);
+
+        // A system without outputs still has its inputs captured
+        system.remove_device("usb_out").unwrap();
+        let snapshot = crate::snapshot().unwrap();
+        assert_eq!(snapshot.default_output, None);
+        assert_eq!(snapshot.default_input.as_deref(), Some("usb_in"));
+    }
+
+    #[test]
+    fn test_mock_info() {
+        let system = speakers_and_headset();
+        system.set_info("headset", DeviceInfo { bus: Some(format!("usb")), icon_name: Some(format!("audio-headset")), ..DeviceInfo::default() }).unwrap();
+        let info = Device::from_uid(format!("headset")).unwrap().get_info().unwrap();
+        assert_eq!(info.bus.as_deref(), Some("usb"));
+        assert_eq!(info.icon_name.as_deref(), Some("audio-headset"));
+        assert_eq!(info.channels, Some(2));
+        assert_eq!(info.channel_map, Some(vec![format!("front-left"), format!("front-right")]));
+        assert_eq!(info.driver, None);
+    }
+
+    #[test]
+    fn test_mock_input() {
+        let _system = speakers_and_headset();
+        assert_eq!(crate::get_input_devices(), vec!["Microphone"]);
+        assert!(crate::set_input_volume(0.5));
+        assert_eq!(crate::get_input_volume(), 0.5);
+        assert_eq!(crate::get_system_volume(), 1.0);
+    }
+
+    #[test]
+    fn test_mock_default_device() {
+        let system = speakers_and_headset();
+        assert_eq!(crate::get_default_output_device().unwrap().get_uid(), Ok(String::from("speakers")));
+
+        let headset = Device::from_name(String::from("Headset")).unwrap();
+        crate::set_default_output_device(&headset).unwrap();
+        assert_eq!(crate::get_default_output_device().unwrap().get_uid(), Ok(String::from("headset")));
+
+        let mic = Device::from_uid(String::from("mic")).unwrap();
+        assert!(crate::set_default_output_device(&mic).is_err());
+
+        system.remove_device("headset").unwrap();
+        assert_eq!(crate::get_default_output_device().unwrap().get_uid(), Ok(String::from("speakers")));
+        assert_eq!(headset.get_vol(), Err(Error::DeviceNotFound));
+    }
+
+    #[test]
+    fn test_mock_balance() {
+        let _system = speakers_and_headset();
+        let device = Device::from_uid(String::from("speakers")).unwrap();
+        device.set_vol(0.8).unwrap();
+        device.set_balance(-0.5).unwrap();
+        assert_eq!(device.get_balance(), Ok(-0.5));
+        assert_eq!(device.get_vol(), Ok(0.8));
+        let channels = device.get_channel_vols().unwrap();
+        assert_eq!(channels[0].volume, 0.8);
+        assert_eq!(channels[1].volume, 0.4);
+
+        // Lowering the volume keeps the balance
+        device.set_vol(0.4).unwrap();
+        assert_eq!(device.get_balance(), Ok(-0.5));
+        assert!(device.set_fade(0.5).is_err());
+    }
+
+    #[test]
+    fn test_mock_injected_errors() {
+        let system = speakers_and_headset();
+        system.fail_next(Error::VolumeCaptureFailed(format!("injected")));
+        assert_eq!(crate::try_get_system_volume(), Err(Error::VolumeCaptureFailed(format!("injected"))));
+        assert_eq!(crate::try_get_system_volume(), Ok(1.0));
+
+        system.fail_device("speakers", Error::DeviceAccessFailed(format!("unplugged")));
+        assert!(crate::try_set_mute(true).is_err());
+        let headset = Device::from_uid(String::from("headset")).unwrap();
+        assert_eq!(headset.set_mute(true), Ok(()));
+
+        system.clear_errors();
+        assert_eq!(crate::try_set_mute(true), Ok(true));
+    }
+
+    #[test]
+    fn test_mock_events() {
+        let system = speakers_and_headset();
+        crate::set_connection_options(ConnectionOptions::default().with_identity(ClientIdentity::new("Test Mixer"))).unwrap();
+        let (subscription, receiver) = crate::subscribe_channel().unwrap();
+        system.add_port("headset", Port {
+            name: String::from("headphones"),
+            description: String::from("Headphones"),
+            priority: 100,
+            availability: PortAvailability::Unplugged,
+            port_type: PortType::Headphones,
+        }).unwrap();
+
+        crate::set_system_volume(0.3);
+        system.set_port_availability("headset", "headphones", PortAvailability::Plugged).unwrap();
+        system.set_default("headset").unwrap();
+        Device::from_uid(String::from("speakers")).unwrap().make_default(false).unwrap();
+
+        // Changes made through cpvc carry the identity of this process, the others come from "another client"
+        let identity = ClientIdentity::new("Test Mixer");
+        assert_eq!(system.get_identity(), identity);
+        assert_eq!(receiver.try_recv(), Ok(Event::VolumeChanged { uid: String::from("speakers"), volume: 0.3, origin: Some(identity.clone()) }));
+        assert_eq!(receiver.try_recv(), Ok(Event::PortAvailabilityChanged {
+            uid: String::from("headset"),
+            port: String::from("headphones"),
+            availability: PortAvailability::Plugged,
+        }));
+        assert_eq!(receiver.try_recv(), Ok(Event::DefaultDeviceChanged { uid: String::from("headset"), device_type: DeviceType::Output, origin: None }));
+        assert_eq!(receiver.try_recv(), Ok(Event::DefaultDeviceChanged { uid: String::from("speakers"), device_type: DeviceType::Output, origin: Some(identity) }));
+
+        subscription.unsubscribe();
+        crate::set_mute(true);
+        assert_eq!(receiver.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_mock_threads_isolated() {
+        let _system = speakers_and_headset();
+        let other = std::thread::spawn(|| crate::get_sound_devices()).join().unwrap();
+        assert!(other.is_empty());
+        assert_eq!(crate::get_sound_devices().len(), 2);
+    }
+}
@@ -0,0 +1,161 @@
+// #[cfg(not(all(target_os="linux", feature="pipewire")))]
+#[cfg(all(target_os="linux", feature="pipewire"))]
+
+mod device {
+
+    use crate::{DeviceType, curve::VolumeCurve, device::{self, ChannelVolume, DeviceInfo, DeviceTrait}, error::Error, pipewire::{self, Connection, NodeState}};
+
+    /// Audio sink or source node of the PipeWire graph
+    pub struct PipeWireDevice {
+        name: String,
+        dev_type: DeviceType,
+    }
+
+    impl DeviceTrait for PipeWireDevice {
+
+        // Name is the node description (e.g. "Built-in Audio Analog Stereo")
+        fn from_name(name: String) -> Result<Self, Error> {
+            Connection::new()?.get_nodes()?.into_iter()
+                .find(|node| node.description == name)
+                .map(|node| PipeWireDevice::new(node.name, node.dev_type))
+                .ok_or(Error::DeviceNotFound)
+        }
+
+        // UID is the node name (e.g. "alsa_output.pci-0000_00_1f.3.analog-stereo")
+        fn from_uid(uid: String) -> Result<Self, Error> {
+            let node = Connection::new()?.get_node(&uid)?;
+            Ok(PipeWireDevice::new(node.name, node.dev_type))
+        }
+
+        fn get_name(&self) -> Result<String, Error> {
+            Ok(self.get_state()?.description)
+        }
+
+        fn get_uid(&self) -> Result<String, Error> {
+            Ok(self.name.clone())
+        }
+
+        fn get_device_type(&self) -> Result<DeviceType, Error> {
+            Ok(self.dev_type)
+        }
+
+        fn get_vol(&self) -> Result<f32, Error> {
+            let state = self.get_state()?;
+            if state.mute {
+                return Ok(0.0);
+            }
+            Ok(state.channel_vols.iter().copied().fold(0.0, f32::max))
+        }
+
+        // Scales every channel so the loudest one matches `value`, keeping balance and fade
+        fn set_vol(&self, value: f32) -> Result<(), Error> {
+            device::check_vol(&self.name, value)?;
+            let connection = Connection::new()?;
+            let state = connection.get_node(&self.name)?;
+            let max = state.channel_vols.iter().copied().fold(0.0, f32::max);
+            let channel_vols = state.channel_vols.iter()
+                .map(|volume| if max > 0.0 { volume / max * value } else { value })
+                .collect::<Vec<_>>();
+            connection.set_props(state.id, Some(&channel_vols), None)
+        }
+
+        fn get_mute(&self) -> Result<bool, Error> {
+            Ok(self.get_state()?.mute)
+        }
+
+        fn set_mute(&self, mute: bool) -> Result<(), Error> {
+            let connection = Connection::new()?;
+            let state = connection.get_node(&self.name)?;
+            connection.set_props(state.id, None, Some(mute))
+                .map_err(|e| Error::MuteSetFailed(format!("Failed to adjust node mute {:?}", e)))
+        }
+
+        // Streams follow the new default unless they were explicitly routed, `move_streams` is not needed
+        fn set_default(&self, _move_streams: bool) -> Result<(), Error> {
+            Connection::new()?.set_default_name(self.dev_type, &self.name)
+        }
+
+        fn get_channel_vols(&self) -> Result<Vec<ChannelVolume>, Error> {
+            let state = self.get_state()?;
+            Ok(state.channel_vols.iter().enumerate()
+                .map(|(index, volume)| ChannelVolume {
+                    position: state.channel_map.get(index).map(|position| pipewire::get_position_name(*position)).unwrap_or_default(),
+                    volume: *volume,
+                })
+                .collect())
+        }
+
+        fn set_channel_vol(&self, channel: usize, value: f32) -> Result<(), Error> {
+            device::check_vol(&self.name, value)?;
+            let connection = Connection::new()?;
+            let state = connection.get_node(&self.name)?;
+            let mut channel_vols = state.channel_vols;
+            match channel_vols.get_mut(channel) {
+                Some(volume) => {
+                    *volume = value;
+                },
+                None => {
+                    return Err(Error::VolumeSetFailed(format!("Channel {} out of range, device has {} channels", channel, channel_vols.len())));
+                }
+            }
+            connection.set_props(state.id, Some(&channel_vols), None)
+        }
+
+        fn get_info(&self) -> Result<DeviceInfo, Error> {
+            let state = self.get_state()?;
+            Ok(DeviceInfo {
+                channels: state.info.channels.or(Some(state.channel_vols.len() as u8)),
+                channel_map: Some(state.channel_map.iter().map(|position| pipewire::get_position_name(*position)).collect()),
+                ..state.info
+            })
+        }
+
+        // Node volumes go past unity, `check_vol` enforces the configured ceiling
+        fn supports_boost(&self) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        // Channel volumes are converted from the node's linear props with the cubic mapping
+        fn get_native_curve(&self) -> Result<VolumeCurve, Error> {
+            Ok(VolumeCurve::Cubic)
+        }
+    }
+
+    impl PipeWireDevice {
+        pub(crate) fn new(name: String, dev_type: DeviceType) -> Self {
+            PipeWireDevice {
+                name,
+                dev_type,
+            }
+        }
+
+        pub fn get_node_name(&self) -> String {
+            self.name.clone()
+        }
+
+        /// Global id of the node, changes whenever the node is recreated
+        pub fn get_node_id(&self) -> Result<u32, Error> {
+            Ok(self.get_state()?.id)
+        }
+
+        fn get_state(&self) -> Result<NodeState, Error> {
+            Connection::new()?.get_node(&self.name)
+        }
+    }
+
+}
+
+#[cfg(not(all(target_os="linux", feature="pipewire")))]
+// #[cfg(all(target_os="linux", feature="pipewire"))]
+
+mod device {
+
+    use crate::device::DeviceTrait;
+
+    pub struct PipeWireDevice {}
+
+    impl DeviceTrait for PipeWireDevice {}
+
+}
+
+pub(crate) use device::*;
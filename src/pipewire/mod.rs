@@ -0,0 +1,468 @@
+pub mod device;
+
+// #[cfg(not(all(target_os="linux", feature="pipewire")))]
+#[cfg(all(target_os="linux", feature="pipewire"))]
+// Talks to PipeWire nodes directly, used when the daemon runs without pipewire-pulse
+pub mod pipewire {
+    use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+    use ::pipewire::{
+        self as pw,
+        context::ContextRc, core::CoreRc, main_loop::MainLoopRc, metadata::Metadata, node::Node,
+        properties::PropertiesBox, registry::{GlobalObject, RegistryRc}, types::ObjectType,
+        spa::{
+            param::ParamType,
+            pod::{Object, Pod, Property, Value, ValueArray, deserialize::PodDeserializer, serialize::PodSerializer},
+            sys, utils::{SpaTypes, dict::DictRef},
+        },
+    };
+    use crate::{DeviceType, debug_eprintln, device::{DeviceInfo, DeviceTrait}, error::Error, pipewire::device::PipeWireDevice};
+
+    // A stalled daemon should not hang the caller forever
+    const ROUNDTRIP_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Volume and mute of a node, read from its `Props` param
+    pub(crate) struct NodeState {
+        pub(crate) id: u32,
+        pub(crate) name: String,
+        pub(crate) description: String,
+        pub(crate) dev_type: DeviceType,
+        pub(crate) channel_vols: Vec<f32>,
+        pub(crate) channel_map: Vec<u32>,
+        pub(crate) mute: bool,
+        pub(crate) info: DeviceInfo,
+    }
+
+    /// One connection to the PipeWire daemon, objects are only valid while it is alive
+    pub(crate) struct Connection {
+        mainloop: MainLoopRc,
+        _context: ContextRc,
+        core: CoreRc,
+        registry: RegistryRc,
+    }
+
+    impl Connection {
+        pub(crate) fn new() -> Result<Self, Error> {
+            pw::init();
+            let mainloop = MainLoopRc::new(None)
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to create PipeWire mainloop {}", e)))?;
+            let context = ContextRc::new(&mainloop, None)
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to create PipeWire context {}", e)))?;
+            let core = context.connect_rc(None)
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to connect to PipeWire {}", e)))?;
+            let registry = core.get_registry_rc()
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to get PipeWire registry {}", e)))?;
+            Ok(Connection {
+                mainloop,
+                _context: context,
+                core,
+                registry,
+            })
+        }
+
+        // Waits until the daemon has answered every request sent so far
+        fn roundtrip(&self) -> Result<(), Error> {
+            let done = Arc::new(Mutex::new(false));
+            let clone = Arc::clone(&done);
+            let pending = self.core.sync(0)
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to sync with PipeWire {}", e)))?;
+            let _listener = self.core.add_listener_local()
+                .done(move |id, seq| {
+                    if id == pw::core::PW_ID_CORE && seq == pending {
+                        *clone.lock().unwrap() = true;
+                    }
+                })
+                .register();
+
+            let deadline = Instant::now() + ROUNDTRIP_TIMEOUT;
+            while !*done.lock().unwrap() {
+                if Instant::now() > deadline {
+                    debug_eprintln("PipeWire daemon did not answer in time");
+                    return Err(Error::DeviceAccessFailed(format!("PipeWire daemon did not answer in time")));
+                }
+                self.mainloop.loop_().iterate(Duration::from_millis(100));
+            }
+            Ok(())
+        }
+
+        fn get_globals(&self) -> Result<Vec<GlobalObject<PropertiesBox>>, Error> {
+            let globals = Arc::new(Mutex::new(Vec::new()));
+            let clone = Arc::clone(&globals);
+            let _listener = self.registry.add_listener_local()
+                .global(move |global| {
+                    if matches!(global.type_, ObjectType::Node | ObjectType::Metadata) {
+                        clone.lock().unwrap().push(global.to_owned());
+                    }
+                })
+                .register();
+            self.roundtrip()?;
+            let globals = std::mem::take(&mut *globals.lock().unwrap());
+            Ok(globals)
+        }
+
+        /// Gathers every audio sink (output) and source (input) node
+        pub(crate) fn get_nodes(&self) -> Result<Vec<NodeState>, Error> {
+            let props = Arc::new(Mutex::new(HashMap::new()));
+            let infos = Arc::new(Mutex::new(HashMap::new()));
+            let mut bound = Vec::new();
+            let mut nodes = Vec::new();
+            for global in self.get_globals()? {
+                if global.type_ != ObjectType::Node {
+                    continue;
+                }
+                let Some(properties) = global.props.as_ref() else {
+                    continue;
+                };
+                let dev_type = match properties.get("media.class") {
+                    Some("Audio/Sink") => DeviceType::Output,
+                    Some("Audio/Source") => DeviceType::Input,
+                    _ => continue,
+                };
+                let name = properties.get("node.name").unwrap_or_default().to_string();
+                let description = properties.get("node.description").map(|description| description.to_string()).unwrap_or(name.clone());
+
+                let node: Node = self.registry.bind(&global)
+                    .map_err(|e| Error::DeviceEnumerationFailed(format!("Failed to bind PipeWire node {} {}", global.id, e)))?;
+                let id = global.id;
+                let clone = Arc::clone(&props);
+                let infos_clone = Arc::clone(&infos);
+                let listener = node.add_listener_local()
+                    // Registry globals only carry a few props, the node info has all of them
+                    .info(move |info| {
+                        if let Some(props) = info.props() {
+                            infos_clone.lock().unwrap().insert(id, to_info(props));
+                        }
+                    })
+                    .param(move |_seq, _id, _index, _next, param| {
+                        if let Some(param) = param.and_then(parse_props) {
+                            // Only the first Props object carries the channel volumes
+                            clone.lock().unwrap().entry(id).or_insert(param);
+                        }
+                    })
+                    .register();
+                node.enum_params(0, Some(ParamType::Props), 0, u32::MAX);
+                bound.push((node, listener));
+
+                nodes.push(NodeState {
+                    id,
+                    name,
+                    description,
+                    dev_type,
+                    channel_vols: Vec::new(),
+                    channel_map: Vec::new(),
+                    mute: false,
+                    info: DeviceInfo::default(),
+                });
+            }
+            self.roundtrip()?;
+
+            let mut props = props.lock().unwrap();
+            let mut infos = infos.lock().unwrap();
+            for node in nodes.iter_mut() {
+                node.info = infos.remove(&node.id).unwrap_or_default();
+                if let Some((channel_vols, channel_map, mute)) = props.remove(&node.id) {
+                    node.channel_vols = channel_vols;
+                    node.channel_map = channel_map;
+                    node.mute = mute;
+                }
+            }
+            Ok(nodes)
+        }
+
+        pub(crate) fn get_node(&self, name: &str) -> Result<NodeState, Error> {
+            self.get_nodes()?.into_iter()
+                .find(|node| node.name == name)
+                .ok_or(Error::DeviceNotFound)
+        }
+
+        // The session manager keeps the current default in the "default" metadata object
+        fn with_default_metadata<R>(&self, action: impl FnOnce(&Metadata) -> Result<R, Error>) -> Result<R, Error> {
+            let global = self.get_globals()?.into_iter()
+                .find(|global| global.type_ == ObjectType::Metadata
+                    && global.props.as_ref().and_then(|props| props.get("metadata.name")) == Some("default"))
+                .ok_or(Error::DeviceAccessFailed(format!("No default metadata, is a session manager running?")))?;
+            let metadata: Metadata = self.registry.bind(&global)
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to bind PipeWire metadata {}", e)))?;
+            action(&metadata)
+        }
+
+        /// Node name of the default sink or source
+        pub(crate) fn get_default_name(&self, dev_type: DeviceType) -> Result<String, Error> {
+            let key = get_default_key(dev_type, false);
+            let value = Arc::new(Mutex::new(None));
+            let clone = Arc::clone(&value);
+            self.with_default_metadata(|metadata| {
+                let _listener = metadata.add_listener_local()
+                    .property(move |subject, property, _type, property_value| {
+                        if subject == pw::core::PW_ID_CORE && property == Some(key) {
+                            *clone.lock().unwrap() = property_value.and_then(parse_default_name);
+                        }
+                        0
+                    })
+                    .register();
+                self.roundtrip()
+            })?;
+            let name = value.lock().unwrap().take();
+            name.ok_or(Error::DeviceNotFound)
+        }
+
+        pub(crate) fn set_default_name(&self, dev_type: DeviceType, name: &str) -> Result<(), Error> {
+            // Configured defaults are what the session manager persists and applies
+            let key = get_default_key(dev_type, true);
+            let value = format!("{{\"name\":{}}}", json_string(name));
+            self.with_default_metadata(|metadata| {
+                metadata.set_property(pw::core::PW_ID_CORE, key, Some("Spa:String:JSON"), Some(&value));
+                self.roundtrip()
+            })
+        }
+
+        pub(crate) fn set_props(&self, id: u32, channel_vols: Option<&[f32]>, mute: Option<bool>) -> Result<(), Error> {
+            let global = self.get_globals()?.into_iter()
+                .find(|global| global.id == id)
+                .ok_or(Error::DeviceNotFound)?;
+            let node: Node = self.registry.bind(&global)
+                .map_err(|e| Error::DeviceAccessFailed(format!("Failed to bind PipeWire node {} {}", id, e)))?;
+
+            let mut properties = Vec::new();
+            if let Some(channel_vols) = channel_vols {
+                let linear = channel_vols.iter().map(|volume| to_linear(*volume)).collect();
+                properties.push(Property::new(sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(linear))));
+            }
+            if let Some(mute) = mute {
+                properties.push(Property::new(sys::SPA_PROP_mute, Value::Bool(mute)));
+            }
+            let props = Value::Object(Object {
+                type_: SpaTypes::ObjectParamProps.as_raw(),
+                id: ParamType::Props.as_raw(),
+                properties,
+            });
+            let (bytes, _) = PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &props)
+                .map_err(|e| Error::VolumeSetFailed(format!("Failed to build Props param {:?}", e)))?;
+            let bytes = bytes.into_inner();
+            let pod = Pod::from_bytes(&bytes)
+                .ok_or(Error::VolumeSetFailed(format!("Failed to build Props param")))?;
+            node.set_param(ParamType::Props, 0, pod);
+            self.roundtrip()
+        }
+    }
+
+    fn get_default_key(dev_type: DeviceType, configured: bool) -> &'static str {
+        match (dev_type, configured) {
+            (DeviceType::Input, false) => "default.audio.source",
+            (DeviceType::Input, true) => "default.configured.audio.source",
+            (_, false) => "default.audio.sink",
+            (_, true) => "default.configured.audio.sink",
+        }
+    }
+
+    // Values look like {"name":"alsa_output.pci-0000_00_1f.3.analog-stereo"}
+    fn parse_default_name(value: &str) -> Option<String> {
+        let start = value.find("\"name\"")? + "\"name\"".len();
+        let rest = value[start..].trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+        let mut name = String::new();
+        let mut characters = rest.chars();
+        loop {
+            match characters.next()? {
+                '"' => return Some(name),
+                '\\' => match characters.next()? {
+                    'n' => name.push('\n'),
+                    'r' => name.push('\r'),
+                    't' => name.push('\t'),
+                    'u' => {
+                        let code = characters.by_ref().take(4).collect::<String>();
+                        name.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                    },
+                    character => name.push(character),
+                },
+                character => name.push(character),
+            }
+        }
+    }
+
+    // Node names are free text, quotes and control characters would break the metadata JSON
+    fn json_string(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len() + 2);
+        escaped.push('"');
+        for character in value.chars() {
+            match character {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                character if character.is_control() => escaped.push_str(&format!("\\u{:04x}", character as u32)),
+                character => escaped.push(character),
+            }
+        }
+        escaped.push('"');
+        escaped
+    }
+
+    fn parse_props(param: &Pod) -> Option<(Vec<f32>, Vec<u32>, bool)> {
+        let (_, value) = PodDeserializer::deserialize_any_from(param.as_bytes()).ok()?;
+        let Value::Object(object) = value else {
+            return None;
+        };
+        let mut channel_vols = None;
+        let mut channel_map = Vec::new();
+        let mut mute = false;
+        for property in object.properties {
+            match (property.key, property.value) {
+                (sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(volumes))) => {
+                    channel_vols = Some(volumes.into_iter().map(from_linear).collect());
+                },
+                (sys::SPA_PROP_channelMap, Value::ValueArray(ValueArray::Id(positions))) => {
+                    channel_map = positions.into_iter().map(|position| position.0).collect();
+                },
+                (sys::SPA_PROP_mute, Value::Bool(value)) => {
+                    mute = value;
+                },
+                _ => {}
+            }
+        }
+        Some((channel_vols?, channel_map, mute))
+    }
+
+    // PipeWire stores linear amplitude, the cubic mapping matches what pipewire-pulse and wpctl show
+    fn from_linear(volume: f32) -> f32 {
+        volume.cbrt()
+    }
+
+    fn to_linear(volume: f32) -> f32 {
+        volume.powi(3)
+    }
+
+    // Keys PipeWire's ALSA nodes usually carry, nodes of other APIs leave most fields empty
+    fn to_info(props: &DictRef) -> DeviceInfo {
+        let get = |key: &str| props.get(key).map(|value| value.to_string());
+        DeviceInfo {
+            driver: get("alsa.driver_name").or(get("factory.name")),
+            sample_format: get("audio.format"),
+            sample_rate: props.get("audio.rate").and_then(|rate| rate.parse().ok()),
+            channels: props.get("audio.channels").and_then(|channels| channels.parse().ok()),
+            card: props.get("api.alsa.card").or(props.get("alsa.card")).and_then(|card| card.parse().ok()),
+            bus: get("device.bus"),
+            form_factor: get("device.form-factor").or(get("device.form_factor")),
+            icon_name: get("device.icon-name").or(get("device.icon_name")),
+            ..DeviceInfo::default()
+        }
+    }
+
+    /// Name of a `SPA_AUDIO_CHANNEL_*` position, spelled like PulseAudio's
+    pub(crate) fn get_position_name(position: u32) -> String {
+        match position {
+            sys::SPA_AUDIO_CHANNEL_MONO => "mono",
+            sys::SPA_AUDIO_CHANNEL_FL => "front-left",
+            sys::SPA_AUDIO_CHANNEL_FR => "front-right",
+            sys::SPA_AUDIO_CHANNEL_FC => "front-center",
+            sys::SPA_AUDIO_CHANNEL_LFE => "lfe",
+            sys::SPA_AUDIO_CHANNEL_SL => "side-left",
+            sys::SPA_AUDIO_CHANNEL_SR => "side-right",
+            sys::SPA_AUDIO_CHANNEL_RL => "rear-left",
+            sys::SPA_AUDIO_CHANNEL_RR => "rear-right",
+            sys::SPA_AUDIO_CHANNEL_RC => "rear-center",
+            _ => "aux",
+        }.to_string()
+    }
+
+    /// Reports if a PipeWire daemon accepts connections
+    pub fn is_running() -> bool {
+        Connection::new().and_then(|connection| connection.roundtrip()).is_ok()
+    }
+
+    pub fn get_default_output_dev() -> Result<PipeWireDevice, Error> {
+        let name = Connection::new()?.get_default_name(DeviceType::Output)?;
+        Ok(PipeWireDevice::new(name, DeviceType::Output))
+    }
+
+    pub fn get_default_input_dev() -> Result<PipeWireDevice, Error> {
+        let name = Connection::new()?.get_default_name(DeviceType::Input)?;
+        Ok(PipeWireDevice::new(name, DeviceType::Input))
+    }
+
+    pub fn get_device_identifiers(dev_type: DeviceType) -> Result<Vec<(String, String)>, Error> {
+        Ok(Connection::new()?.get_nodes()?.into_iter()
+            .filter(|node| node.dev_type == dev_type)
+            .map(|node| (node.name, node.description))
+            .collect())
+    }
+
+    // Volume Controls
+    pub fn get_sound_devices() -> Result<Vec<String>, Error> {
+        Ok(get_device_identifiers(DeviceType::Output)?.into_iter().map(|(_id, name)| name).collect())
+    }
+
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Ok(get_device_identifiers(DeviceType::Output)?.into_iter().map(|(id, _name)| id).collect())
+    }
+
+    pub fn get_vol() -> Result<f32, Error> {
+        get_default_output_dev()?.get_vol()
+    }
+
+    pub fn set_vol(value: f32) -> Result<(), Error> {
+        get_default_output_dev()?.set_vol(value)
+    }
+
+    pub fn get_mute() -> Result<bool, Error> {
+        get_default_output_dev()?.get_mute()
+    }
+
+    pub fn set_mute(state: bool) -> Result<(), Error> {
+        get_default_output_dev()?.set_mute(state)
+    }
+
+    // Input Controls
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        Ok(get_device_identifiers(DeviceType::Input)?.into_iter().map(|(_id, name)| name).collect())
+    }
+
+    pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+        Ok(get_device_identifiers(DeviceType::Input)?.into_iter().map(|(id, _name)| id).collect())
+    }
+
+    pub fn get_input_vol() -> Result<f32, Error> {
+        get_default_input_dev()?.get_vol()
+    }
+
+    pub fn set_input_vol(value: f32) -> Result<(), Error> {
+        get_default_input_dev()?.set_vol(value)
+    }
+
+    pub fn get_input_mute() -> Result<bool, Error> {
+        get_default_input_dev()?.get_mute()
+    }
+
+    pub fn set_input_mute(state: bool) -> Result<(), Error> {
+        get_default_input_dev()?.set_mute(state)
+    }
+
+}
+
+#[cfg(not(all(target_os="linux", feature="pipewire")))]
+// #[cfg(all(target_os="linux", feature="pipewire"))]
+pub mod pipewire {
+    use crate::error::Error;
+
+    pub fn is_running() -> bool {
+        false
+    }
+
+    pub fn get_sound_devices() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+}
+
+pub(crate) use self::pipewire::*;
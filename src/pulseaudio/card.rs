@@ -0,0 +1,204 @@
+// #[cfg(not(target_os="linux"))]
+#[cfg(target_os="linux")]
+
+mod card {
+
+    use std::sync::{Arc, Mutex};
+    use libpulse_binding::{
+        callbacks::ListResult,
+        context::introspect::{CardInfo, CardProfileInfo},
+        proplist::properties,
+    };
+    use crate::{debug_eprintln, debug_println, error::Error, pulseaudio::{self, session::PulseSession}};
+
+    /// A configuration of a card (e.g. "output:hdmi-stereo" or "a2dp-sink"), picking one decides which sinks and sources exist
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Profile {
+        pub name: String,
+        pub description: String,
+        /// `false` when the profile cannot be used right now (e.g. nothing plugged into its jack)
+        pub available: bool,
+        pub sinks: u32,
+        pub sources: u32,
+        pub priority: u32,
+    }
+
+    fn to_profile(profile: &CardProfileInfo) -> Profile {
+        let name = profile.name.as_ref().map(|name| name.to_string()).unwrap_or_default();
+        Profile {
+            description: profile.description.as_ref().map(|description| description.to_string()).unwrap_or_else(|| name.clone()),
+            name,
+            available: profile.available,
+            sinks: profile.n_sinks,
+            sources: profile.n_sources,
+            priority: profile.priority,
+        }
+    }
+
+    /// A sound card (PulseAudio card), the hardware behind one or more sinks and sources
+    ///
+    /// Card details and profiles are captured when the card is enumerated,
+    /// the active profile is always read from the server
+    #[derive(Debug, Clone)]
+    pub struct Card {
+        index: u32,
+        name: String,
+        description: Option<String>,
+        driver: Option<String>,
+        profiles: Vec<Profile>,
+        session: Arc<PulseSession>,
+    }
+
+    impl Card {
+        fn from_info(info: &CardInfo, session: Arc<PulseSession>) -> Option<Self> {
+            Some(Card {
+                index: info.index,
+                name: info.name.as_ref()?.to_string(),
+                description: info.proplist.get_str(properties::DEVICE_DESCRIPTION),
+                driver: info.driver.as_ref().map(|driver| driver.to_string()),
+                profiles: info.profiles.iter().map(to_profile).collect(),
+                session,
+            })
+        }
+    }
+
+    /// Gathers every card of the server
+    pub fn get_cards() -> Result<Vec<Card>, Error> {
+        get_session_cards(&PulseSession::shared()?)
+    }
+
+    /// Same as `get_cards`, cards keep using `session` for their requests
+    pub fn get_session_cards(session: &Arc<PulseSession>) -> Result<Vec<Card>, Error> {
+        let card_session = Arc::clone(session);
+        session.run(move |mainloop, context| {
+            let cards = Arc::new(Mutex::new(Vec::new()));
+            let clone = Arc::clone(&cards);
+            let error = Arc::new(Mutex::new(None));
+            let err_clone = error.clone();
+
+            let op = context.introspect().get_card_info_list(move |info| {
+                    match info {
+                        ListResult::Item(card) => {
+                            if let Some(card) = Card::from_info(card, Arc::clone(&card_session)) {
+                                clone.lock().unwrap().push(card);
+                            }
+                        },
+                        ListResult::End => {
+                            debug_println("Cards finished")
+                        },
+                        ListResult::Error => {
+                            err_clone.lock().unwrap().replace(Error::DeviceEnumerationFailed(format!("ListResult Access Error")));
+                            debug_eprintln("error gathering card information");
+                        },
+                    }
+                });
+            pulseaudio::wait_for_operation(mainloop, &op);
+
+            if let Some(error) = error.lock().unwrap().take() {
+                return Err(error);
+            }
+            Ok(std::mem::take(&mut *cards.lock().unwrap()))
+        })?
+    }
+
+    impl Card {
+        // Name is the card name (e.g. "alsa_card.pci-0000_00_1f.3")
+        pub fn from_name(name: String) -> Result<Self, Error> {
+            get_cards()?.into_iter()
+                .find(|card| card.name == name)
+                .ok_or(Error::DeviceNotFound)
+        }
+
+        pub fn get_index(&self) -> u32 {
+            self.index
+        }
+
+        pub fn get_name(&self) -> String {
+            self.name.clone()
+        }
+
+        /// Human readable name (e.g. "Built-in Audio")
+        pub fn get_description(&self) -> Option<String> {
+            self.description.clone()
+        }
+
+        /// Server module driving the card (e.g. "module-alsa-card.c")
+        pub fn get_driver(&self) -> Option<String> {
+            self.driver.clone()
+        }
+
+        pub fn get_profiles(&self) -> Vec<Profile> {
+            self.profiles.clone()
+        }
+
+        /// `None` when the card reports no active profile
+        pub fn get_active_profile(&self) -> Result<Option<Profile>, Error> {
+            let index = self.index;
+            let profile = self.session.run(move |mainloop, context| {
+                let profile = Arc::new(Mutex::new(None));
+                let clone = Arc::clone(&profile);
+                let found = Arc::new(Mutex::new(false));
+                let found_clone = Arc::clone(&found);
+                let op = context.introspect().get_card_info_by_index(index, move |info| {
+                        match info {
+                            ListResult::Item(card) => {
+                                *found_clone.lock().unwrap() = true;
+                                *clone.lock().unwrap() = card.active_profile.as_ref().map(|profile| to_profile(profile));
+                            },
+                            ListResult::End => {},
+                            ListResult::Error => {
+                                debug_eprintln("error gathering card information");
+                            },
+                        }
+                    });
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let found = *found.lock().unwrap();
+                let profile = profile.lock().unwrap().take();
+                if found { Ok(profile) } else { Err(Error::DeviceNotFound) }
+            })??;
+            Ok(profile)
+        }
+
+        /// Switches the card to the profile named `name`, sinks and sources of the old profile go away
+        ///
+        /// A request the server rejects fails with `Error::PulseAudio` and the libpulse error code
+        pub fn set_profile(&self, name: String) -> Result<(), Error> {
+            if !self.profiles.iter().any(|profile| profile.name == name) {
+                return Err(Error::DeviceAccessFailed(format!("{} has no profile {}", self.name, name)));
+            }
+            let index = self.index;
+            self.session.run(move |mainloop, context| {
+                let success = Arc::new(Mutex::new(false));
+                let clone = Arc::clone(&success);
+                let op = context.introspect().set_card_profile_by_index(index, &name, Some(Box::new(move |status| {
+                    *clone.lock().unwrap() = status;
+                })));
+                pulseaudio::wait_for_operation(mainloop, &op);
+                if !*success.lock().unwrap() {
+                    return Err(pulseaudio::to_error(context.errno()));
+                }
+                Ok(())
+            })?
+        }
+    }
+
+}
+
+#[cfg(not(target_os="linux"))]
+// #[cfg(target_os="linux")]
+
+mod card {
+
+    use crate::error::Error;
+
+    #[derive(Debug, Clone)]
+    pub struct Card {}
+
+    pub fn get_cards() -> Result<Vec<Card>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+}
+
+pub use card::*;
@@ -3,322 +3,560 @@
 
 mod device {
 
-    use std::sync::{Arc, Mutex};
-    use crate::{debug_eprintln, debug_println, device::DeviceTrait, error::Error, pulseaudio};
+    use std::{borrow::Cow, sync::{Arc, Mutex}};
+    use libpulse_binding::{channelmap::{Map, Position}, def::{PortAvailable, SinkFlagSet, SourceFlagSet}, proplist::{Proplist, properties}, sample::Spec, volume::{ChannelVolumes, Volume, VolumeDB}};
+    use libpulse_sys::volume::PA_VOLUME_NORM;
+    use crate::{DeviceType, curve::VolumeCurve, debug_eprintln, debug_println, device::{self, ChannelVolume, DeviceInfo, DeviceTrait, Port, PortAvailability, PortType}, error::Error, pulseaudio::{self, session::PulseSession, subscribe::ChangeKind}};
 
     pub struct PulseAudioDevice {
         dev_str: String,
+        dev_type: DeviceType,
+        session: Arc<PulseSession>,
+    }
+
+    // Snapshot of the sink or source a PulseAudioDevice points to
+    struct DeviceState {
+        index: u32,
+        volume: ChannelVolumes,
+        channel_map: Map,
+        mute: bool,
+        ports: Vec<Port>,
+        active_port: Option<String>,
+        // Volume at which the hardware is at 0 dB, PA_VOLUME_NORM without hardware volume
+        base_volume: Volume,
+        hw_db_volume: bool,
+        info: DeviceInfo,
+    }
+
+    pub(crate) fn to_availability(available: PortAvailable) -> PortAvailability {
+        match available {
+            PortAvailable::Yes => PortAvailability::Plugged,
+            PortAvailable::No => PortAvailability::Unplugged,
+            PortAvailable::Unknown => PortAvailability::Unknown,
+        }
+    }
+
+    // Port names follow the ALSA mixer paths (e.g. "analog-output-headphones", "hdmi-output-0")
+    fn to_port_type(name: &str) -> PortType {
+        let name = name.to_lowercase();
+        if name.contains("hdmi") {
+            PortType::Hdmi
+        } else if name.contains("iec958") || name.contains("spdif") {
+            PortType::Spdif
+        } else if name.contains("headset") {
+            PortType::Headset
+        } else if name.contains("headphone") {
+            PortType::Headphones
+        } else if name.contains("speaker") {
+            PortType::Speaker
+        } else if name.contains("mic") {
+            PortType::Microphone
+        } else if name.contains("line") {
+            PortType::Line
+        } else {
+            PortType::Unknown
+        }
+    }
+
+    fn to_port(name: &Option<Cow<str>>, description: &Option<Cow<str>>, priority: u32, available: PortAvailable) -> Port {
+        let name = name.as_ref().map(|name| name.to_string()).unwrap_or_default();
+        Port {
+            port_type: to_port_type(&name),
+            description: description.as_ref().map(|description| description.to_string()).unwrap_or_else(|| name.clone()),
+            name,
+            priority,
+            availability: to_availability(available),
+        }
     }
 
     impl DeviceTrait for PulseAudioDevice {
 
         // Name is device description (e.g. "Dummy Output")
         fn from_name(name: String) -> Result<Self, Error> {
-            let devices = pulseaudio::get_device_identifiers()?;
-
-            for (id, names) in devices {
-                if name == names {
-                    return Ok(PulseAudioDevice {
-                        dev_str: id,
-                    });
-                }
-            }
-            
-            Err(Error::DeviceNotFound)
-
+            PulseSession::shared()?.get_device_by_name(name)
         }
 
-        // UID is device name (e.g. "auto_null")
+        // UID is device name (e.g. "auto_null" or "auto_null.monitor")
         fn from_uid(id: String) -> Result<Self, Error> {
-            let devices = pulseaudio::get_device_identifiers()?;
-
-            for (dev_str, _name) in devices {
-                if id == dev_str {
-                    return Ok(PulseAudioDevice {
-                        dev_str,
-                    });
-                }
-            }
-            
-            Err(Error::DeviceNotFound)
+            PulseSession::shared()?.get_device(id)
         }
 
+        // Stays on the session of the device, which may be connected to another server than the shared one
+        fn get_opener(&self) -> Result<device::DeviceOpener<Self>, Error> {
+            let session = Arc::clone(&self.session);
+            let uid = self.dev_str.clone();
+            Ok(Box::new(move || session.get_device(uid)))
+        }
 
         fn get_uid(&self) -> Result<String, Error> {
             Ok(self.dev_str.clone())
         }
 
         fn get_name(&self) -> Result<String, Error> {
-            pulseaudio::get_device_name(self.dev_str.clone())
+            let devices = match self.dev_type {
+                DeviceType::Input => self.session.get_source_identifiers(true)?,
+                _ => self.session.get_device_identifiers()?,
+            };
+            for (dev_str, name) in devices {
+                if self.dev_str == dev_str {
+                    return Ok(name);
+                }
+            }
+            Err(Error::DeviceNotFound)
         }
 
+        fn get_device_type(&self) -> Result<DeviceType, Error> {
+            Ok(self.dev_type)
+        }
 
         fn get_vol(&self) -> Result<f32, Error> {
-            let mut vol = 0.0;
-            let volume: Arc<Mutex<f32>> = Arc::new(Mutex::new(vol));
-            let clone = Arc::clone(&volume);
-            let dev_str = self.dev_str.clone();
-            let (mut mainloop, context) = pulseaudio::acquire_mainloop_and_context();
-
-            let changed = Arc::new(Mutex::new(false));
-            let changed_clone = changed.clone();
-
-            let error = Arc::new(Mutex::new(None));
-            let err_clone = error.clone();
-
-            let op = context.introspect().get_sink_info_list( move |info | {
-                    match info {
-                        libpulse_binding::callbacks::ListResult::Item(device) => {
-                            if let Some(name) = &device.name && name.to_string() == dev_str{
-                                if device.mute {
-                                    *clone.lock().unwrap() = 0.0;
-                                } else {
-                                    let mut vol_str = device.volume.avg().print().trim().to_string();
-                                    vol_str.remove(vol_str.len() - 1);
-                                    match vol_str.parse::<u8>() {
-                                        Ok(vol) => {
-                                            *clone.lock().unwrap() = vol as f32 / 100.0;
-                                        },
-                                        Err(err) => {
-                                            err_clone.lock().unwrap().replace(Error::VolumeCaptureFailed(format!("Failed to parse volume string {}", err))); 
-                                            debug_eprintln(&format!("Failed to parse volume string {}", err));
-                                        }
-                                    }
-                                }
-                                *changed_clone.lock().unwrap() = true;
-                            }
-                        },
-                        libpulse_binding::callbacks::ListResult::End => {
-                            debug_println("Devices finished")
-                        },
-                        libpulse_binding::callbacks::ListResult::Error => {
-                            err_clone.lock().unwrap().replace(Error::VolumeCaptureFailed(format!("ListResult Access Error"))); 
-                            debug_eprintln("error gathering device information");
-                        },
-                    }
-                });
-            
-            while op.get_state() == libpulse_binding::operation::State::Running {
-                mainloop.iterate(false);
+            let state = self.get_state()?;
+            if state.mute {
+                return Ok(0.0);
             }
+            // Loudest channel, matches the value set_vol scales to, above 1.0 when boosted
+            Ok(state.volume.max().0 as f32 / PA_VOLUME_NORM as f32)
+        }
 
-            mainloop.quit(libpulse_binding::def::Retval(0));
-
-            vol = *volume.lock().unwrap();
-            if let Some(error) = error.lock().unwrap().take() {
-                Err(error)
-            } else if *changed.lock().unwrap() {
-                Ok(vol)
-            } else {
-                Err(Error::VolumeCaptureFailed(format!("Failed to detect device")))
+        // Scales every channel so the loudest one matches `value`, keeping balance and fade
+        fn set_vol(&self, value: f32) -> Result<(), Error> {
+            device::check_vol(&self.dev_str, value)?;
+            let state = self.get_state()?;
+            if value == 0.0 {
+                return self.apply_mute(state.index, true)
+                    .map_err(|_| Error::VolumeSetFailed(format!("Failed to adjust device volume")));
             }
+            let mut channel_vols = state.volume;
+            channel_vols.scale(Volume((value * PA_VOLUME_NORM as f32) as u32))
+                .ok_or(Error::VolumeSetFailed(format!("Failed to scale channel volumes")))?;
+            self.apply_volume(state.index, channel_vols)
         }
 
-        fn set_vol(&self, value: f32) -> Result<(), Error> {
-            let mut success = None;
-            let vol_channels = Arc::new(Mutex::new(None));
-            let clone = Arc::clone(&vol_channels);
-            let dev_str = self.dev_str.clone();
+        fn get_mute(&self) -> Result<bool, Error> {
+            Ok(self.get_state()?.mute)
+        }
 
-            let changed = Arc::new(Mutex::new(false));
-            let changed_clone = changed.clone();
-
-            let error = Arc::new(Mutex::new(None));
-            let err_clone = error.clone();
-
-            let (mut mainloop, context) = pulseaudio::acquire_mainloop_and_context();
-            let op = context.introspect().get_sink_info_list( move |info | {
-                    match info {
-                        libpulse_binding::callbacks::ListResult::Item(device) => {
-                            if let Some(name) = &device.name && name.to_string() == dev_str {
-                                use libpulse_binding::volume::{Volume};
-                                use libpulse_sys::volume::PA_VOLUME_NORM;
-                                let vol = Volume((value * PA_VOLUME_NORM as f32) as u32);
-                                let mut channel_vols = device.volume;
-                                channel_vols.set(device.sample_spec.channels, vol.into());
-                                clone.lock().unwrap().replace((device.index, channel_vols));
-                                *changed_clone.lock().unwrap() = true;
-                            }
-                        },
-                        libpulse_binding::callbacks::ListResult::End => {
-                            debug_println("channel volume aquired");
-                        },
-                        libpulse_binding::callbacks::ListResult::Error => {
-                            err_clone.lock().unwrap().replace(Error::VolumeCaptureFailed(format!("ListResult Access Error"))); 
-                            debug_eprintln("error gathering device information");    
-                        },
-                    }
-                });
+        fn set_mute(&self, mute: bool) -> Result<(), Error> {
+            let state = self.get_state()?;
+            self.apply_mute(state.index, mute)
+        } 
 
-            
-            while op.get_state() == libpulse_binding::operation::State::Running {
-                mainloop.iterate(false);
-            }
+        fn set_default(&self, move_streams: bool) -> Result<(), Error> {
+            self.make_default(move_streams)
+        }
 
-            if let Some((index, volume)) = vol_channels.lock().unwrap().take() {
-                let vol_runner;
-                if value == 0.0 {
-                    vol_runner = context.introspect().set_sink_mute_by_index(index, true, None);
-                } else {
-                    vol_runner = context.introspect().set_sink_volume_by_index(index, &volume, None);
-                }             
-                while vol_runner.get_state() == libpulse_binding::operation::State::Running {
-                    mainloop.iterate(false);
-                    success = Some(true);
-                }
+        fn get_ports(&self) -> Result<Vec<Port>, Error> {
+            Ok(self.get_state()?.ports)
+        }
+
+        fn get_active_port(&self) -> Result<Option<Port>, Error> {
+            let state = self.get_state()?;
+            Ok(state.ports.into_iter().find(|port| Some(&port.name) == state.active_port.as_ref()))
+        }
+
+        fn set_active_port(&self, name: String) -> Result<(), Error> {
+            let state = self.get_state()?;
+            if !state.ports.iter().any(|port| port.name == name) {
+                return Err(Error::DeviceAccessFailed(format!("{} has no port {}", self.dev_str, name)));
+            }
+            let dev_type = self.dev_type;
+            let index = state.index;
+            let port = name.clone();
+            self.record_change(ChangeKind::ActivePort);
+            let success = self.session.run(move |mainloop, context| {
+                let success = Arc::new(Mutex::new(false));
+                let clone = Arc::clone(&success);
+                let callback = Some(Box::new(move |status| *clone.lock().unwrap() = status) as Box<dyn FnMut(bool)>);
+                let op = match dev_type {
+                    DeviceType::Input => context.introspect().set_source_port_by_index(index, &port, callback),
+                    _ => context.introspect().set_sink_port_by_index(index, &port, callback),
+                };
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let success = *success.lock().unwrap();
+                success
+            })?;
+
+            if success {
+                Ok(())
             } else {
-                success = Some(false);
+                Err(Error::DeviceAccessFailed(format!("Failed to switch {} to port {}", self.dev_str, name)))
             }
+        }
 
-            mainloop.quit(libpulse_binding::def::Retval(0));
+        fn get_channel_vols(&self) -> Result<Vec<ChannelVolume>, Error> {
+            let state = self.get_state()?;
+            Ok(state.channel_map.get().iter().zip(state.volume.get().iter())
+                .map(|(position, volume)| ChannelVolume {
+                    position: Position::to_string(*position).map(|name| name.to_string()).unwrap_or_default(),
+                    volume: volume.0 as f32 / PA_VOLUME_NORM as f32,
+                })
+                .collect())
+        }
 
-            if let Some(error) = error.lock().unwrap().take() {
-                Err(error)
-            } else if !*changed.lock().unwrap() {
-                Err(Error::VolumeCaptureFailed(format!("Failed to detect device")))
-            } else {
-                match success {
-                    Some(val) => {
-                        if val {
-                            Ok(())
-                        } else {
-                            Err(Error::VolumeCaptureFailed(format!("Failed to adjust device volume")))
-                        }
-                    }
-                    None => {
-                        Err(Error::VolumeCaptureFailed(format!("Failed to adjust device volume")))
-                    }
+        fn set_channel_vol(&self, channel: usize, value: f32) -> Result<(), Error> {
+            device::check_vol(&self.dev_str, value)?;
+            let state = self.get_state()?;
+            let mut channel_vols = state.volume;
+            match channel_vols.get_mut().get_mut(channel) {
+                Some(volume) => {
+                    *volume = Volume((value * PA_VOLUME_NORM as f32) as u32);
+                },
+                None => {
+                    return Err(Error::VolumeSetFailed(format!("Channel {} out of range, device has {} channels", channel, state.volume.len())));
                 }
             }
+            self.apply_volume(state.index, channel_vols)
         }
 
-        fn get_mute(&self) -> Result<bool, Error> {
-            let mute;
-            let mute_status = Arc::new(Mutex::new(0));
-            let clone = Arc::clone(&mute_status);
-            let dev_str = self.dev_str.clone();
+        fn get_balance(&self) -> Result<f32, Error> {
+            let state = self.get_state()?;
+            Ok(state.volume.get_balance(&state.channel_map))
+        }
 
-            let (mut mainloop, context) = pulseaudio::acquire_mainloop_and_context();
+        fn set_balance(&self, balance: f32) -> Result<(), Error> {
+            if !(-1.0..=1.0).contains(&balance) {
+                return Err(Error::VolumeSetFailed(format!("Balance {} outside of -1.0 to 1.0", balance)));
+            }
+            let state = self.get_state()?;
+            if !state.channel_map.can_balance() {
+                return Err(Error::VolumeSetFailed(format!("Device channel map {} has no left/right balance", state.channel_map.print())));
+            }
+            let mut channel_vols = state.volume;
+            channel_vols.set_balance(&state.channel_map, balance)
+                .ok_or(Error::VolumeSetFailed(format!("Failed to set balance")))?;
+            self.apply_volume(state.index, channel_vols)
+        }
 
-            let changed = Arc::new(Mutex::new(false));
-            let changed_clone = changed.clone();
+        fn get_fade(&self) -> Result<f32, Error> {
+            let state = self.get_state()?;
+            Ok(state.volume.get_fade(&state.channel_map))
+        }
 
-            let error = Arc::new(Mutex::new(None));
-            let err_clone = error.clone();
+        fn set_fade(&self, fade: f32) -> Result<(), Error> {
+            if !(-1.0..=1.0).contains(&fade) {
+                return Err(Error::VolumeSetFailed(format!("Fade {} outside of -1.0 to 1.0", fade)));
+            }
+            let state = self.get_state()?;
+            if !state.channel_map.can_fade() {
+                return Err(Error::VolumeSetFailed(format!("Device channel map {} has no front/rear fade", state.channel_map.print())));
+            }
+            let mut channel_vols = state.volume;
+            channel_vols.set_fade(&state.channel_map, fade)
+                .ok_or(Error::VolumeSetFailed(format!("Failed to set fade")))?;
+            self.apply_volume(state.index, channel_vols)
+        }
 
-            let op = context.introspect().get_sink_info_list( move |info | {
-                    match info {
-                        libpulse_binding::callbacks::ListResult::Item(device) => {
-                            
-                            if let Some(name) = &device.name && name.to_string() == dev_str {
-                                if device.mute {
-                                    *clone.lock().unwrap() = 1;
-                                }
-                                *changed_clone.lock().unwrap() = true;
-                            }
-                        },
-                        libpulse_binding::callbacks::ListResult::End => {
-                            debug_println("Devices finished")
-                        },
-                        libpulse_binding::callbacks::ListResult::Error => {
-                            err_clone.lock().unwrap().replace(Error::VolumeCaptureFailed(format!("ListResult Access Error"))); 
-                            debug_eprintln("error gathering device information");
-                        },
-                    }
-                });
-            
-            while op.get_state() == libpulse_binding::operation::State::Running {
-                mainloop.iterate(false);
+        // dB are relative to the base volume, so 0 dB is the hardware's reference level
+        fn get_vol_db(&self) -> Result<f32, Error> {
+            let state = self.get_state()?;
+            if state.mute {
+                return Ok(f32::NEG_INFINITY);
             }
+            Ok(to_db(state.volume.max(), state.base_volume))
+        }
 
-            mainloop.quit(libpulse_binding::def::Retval(0));
+        fn set_vol_db(&self, db: f32) -> Result<(), Error> {
+            if db.is_nan() {
+                return Err(Error::VolumeSetFailed(format!("Volume {} dB is not a number", db)));
+            }
+            let state = self.get_state()?;
+            let volume = from_db(db, state.base_volume);
+            device::check_vol(&self.dev_str, volume.0 as f32 / PA_VOLUME_NORM as f32)?;
+            let mut channel_vols = state.volume;
+            channel_vols.scale(volume)
+                .ok_or(Error::VolumeSetFailed(format!("Failed to scale channel volumes")))?;
+            self.apply_volume(state.index, channel_vols)
+        }
 
-            mute = *mute_status.lock().unwrap();
-            if let Some(error) = error.lock().unwrap().take() {
-                Err(error)
-            } else if !*changed.lock().unwrap() {
-                Err(Error::VolumeCaptureFailed(format!("Failed to detect device")))
-            } else {
-                match mute {
-                    1 => {
-                        Ok(true)
-                    }
-                    _ => {
-                        Ok(false)
-                    }
-                }
+        fn has_db_volume(&self) -> Result<bool, Error> {
+            Ok(self.get_state()?.hw_db_volume)
+        }
+
+        fn get_info(&self) -> Result<DeviceInfo, Error> {
+            Ok(self.get_state()?.info)
+        }
+
+        // The server accepts up to PA_VOLUME_MAX, `check_vol` enforces the configured ceiling
+        fn supports_boost(&self) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        // pa_volume_t is cubic, PA_VOLUME_NORM / 2 is about -18 dB
+        fn get_native_curve(&self) -> Result<VolumeCurve, Error> {
+            Ok(VolumeCurve::Cubic)
+        }
+    }
+
+    // Fields sinks and sources share, the flags have separate types for each
+    fn to_info(sample_spec: &Spec, channel_map: &Map, proplist: &Proplist, base_volume: Volume) -> DeviceInfo {
+        DeviceInfo {
+            sample_format: sample_spec.format.to_string().map(|format| format.to_string()),
+            sample_rate: Some(sample_spec.rate),
+            channels: Some(sample_spec.channels),
+            channel_map: Some(channel_map.get().iter()
+                .map(|position| Position::to_string(*position).map(|name| name.to_string()).unwrap_or_default())
+                .collect()),
+            base_volume: Some(base_volume.0 as f32 / PA_VOLUME_NORM as f32),
+            bus: proplist.get_str(properties::DEVICE_BUS),
+            form_factor: proplist.get_str(properties::DEVICE_FORM_FACTOR),
+            icon_name: proplist.get_str(properties::DEVICE_ICON_NAME),
+            ..DeviceInfo::default()
+        }
+    }
+
+    // The server converts with its cubic mapping, where PA_VOLUME_NORM is 0 dB
+    fn to_db(volume: Volume, base_volume: Volume) -> f32 {
+        (VolumeDB::from(volume).0 - VolumeDB::from(base_volume).0) as f32
+    }
+
+    fn from_db(db: f32, base_volume: Volume) -> Volume {
+        Volume::from(VolumeDB(db as f64 + VolumeDB::from(base_volume).0))
+    }
+
+    impl PulseAudioDevice {
+        pub(crate) fn new(dev_str: String, dev_type: DeviceType, session: Arc<PulseSession>) -> Self {
+            PulseAudioDevice {
+                dev_str,
+                dev_type,
+                session,
             }
         }
 
-        fn set_mute(&self, mute: bool) -> Result<(), Error> {
-            let mut status = false;
-            let dev_index = Arc::new(Mutex::new(None));
-            let dev_str = self.dev_str.clone();
-            let clone = Arc::clone(&dev_index);
+        pub fn get_device_str(&self) -> String {
+            self.dev_str.clone()
+        }
 
-            let (mut mainloop, context) = pulseaudio::acquire_mainloop_and_context();
+        /// Session the device sends its requests through
+        pub fn get_session(&self) -> Arc<PulseSession> {
+            Arc::clone(&self.session)
+        }
 
-            let changed = Arc::new(Mutex::new(false));
-            let changed_clone = changed.clone();
+        // Recorded before the request, the notification can arrive on the subscription connection before the reply
+        fn record_change(&self, kind: ChangeKind) {
+            pulseaudio::subscribe::record_change(&self.dev_str, kind, &self.session.get_options().identity);
+        }
 
-            let error = Arc::new(Mutex::new(None));
-            let err_clone = error.clone();
+        pub fn is_input(&self) -> bool {
+            self.dev_type == DeviceType::Input
+        }
 
-            let op = context.introspect().get_sink_info_list( move |info | {
-                    match info {
-                        libpulse_binding::callbacks::ListResult::Item(device) => {
-                            if let Some(name) = &device.name && name.to_string() == dev_str{
-                                clone.lock().unwrap().replace(device.index);
-                                *changed_clone.lock().unwrap() = true;
+        /// Makes the device the server default for its direction
+        ///
+        /// With `move_streams` every playing (or recording) stream is moved onto the device as well
+        pub fn make_default(&self, move_streams: bool) -> Result<(), Error> {
+            let state = self.get_state()?;
+            let dev_str = self.dev_str.clone();
+            let dev_type = self.dev_type;
+
+            self.record_change(ChangeKind::Default);
+            self.session.run(move |mainloop, context| {
+                let success = Arc::new(Mutex::new(false));
+                let clone = Arc::clone(&success);
+                let op = match dev_type {
+                    DeviceType::Input => context.set_default_source(&dev_str, move |status| *clone.lock().unwrap() = status),
+                    _ => context.set_default_sink(&dev_str, move |status| *clone.lock().unwrap() = status),
+                };
+                pulseaudio::wait_for_operation(mainloop, &op);
+                if !*success.lock().unwrap() {
+                    return Err(Error::DeviceAccessFailed(format!("Failed to set {} as default device", dev_str)));
+                }
+                if !move_streams {
+                    return Ok(());
+                }
+
+                let streams = Arc::new(Mutex::new(Vec::new()));
+                let streams_clone = Arc::clone(&streams);
+                match dev_type {
+                    DeviceType::Input => {
+                        let op = context.introspect().get_source_output_info_list(move |info| {
+                            if let libpulse_binding::callbacks::ListResult::Item(stream) = info && stream.source != state.index {
+                                streams_clone.lock().unwrap().push(stream.index);
+                            }
+                        });
+                        pulseaudio::wait_for_operation(mainloop, &op);
+                    },
+                    _ => {
+                        let op = context.introspect().get_sink_input_info_list(move |info| {
+                            if let libpulse_binding::callbacks::ListResult::Item(stream) = info && stream.sink != state.index {
+                                streams_clone.lock().unwrap().push(stream.index);
                             }
-                        },
-                        libpulse_binding::callbacks::ListResult::End => {
-                            debug_println("channel volume aquired");
-                        },
-                        libpulse_binding::callbacks::ListResult::Error => {
-                            err_clone.lock().unwrap().replace(Error::VolumeCaptureFailed(format!("ListResult Access Error"))); 
-                            debug_eprintln("error gathering device information");    
-                        },
+                        });
+                        pulseaudio::wait_for_operation(mainloop, &op);
+                    },
+                }
+
+                let mut failed = Vec::new();
+                for stream in std::mem::take(&mut *streams.lock().unwrap()) {
+                    let moved = Arc::new(Mutex::new(false));
+                    let clone = Arc::clone(&moved);
+                    let callback = Some(Box::new(move |status| *clone.lock().unwrap() = status) as Box<dyn FnMut(bool)>);
+                    let op = match dev_type {
+                        DeviceType::Input => context.introspect().move_source_output_by_index(stream, state.index, callback),
+                        _ => context.introspect().move_sink_input_by_index(stream, state.index, callback),
+                    };
+                    pulseaudio::wait_for_operation(mainloop, &op);
+                    if !*moved.lock().unwrap() {
+                        debug_eprintln(&format!("Failed to move stream {}", stream));
+                        failed.push(stream.to_string());
                     }
-                });
+                }
 
-            
-            while op.get_state() == libpulse_binding::operation::State::Running {
-                mainloop.iterate(false);
-            }
+                if failed.is_empty() {
+                    Ok(())
+                } else {
+                    Err(Error::DeviceAccessFailed(format!("Failed to move streams {} to {}", failed.join(", "), dev_str)))
+                }
+            })?
+        }
 
+        fn get_state(&self) -> Result<DeviceState, Error> {
+            let dev_str = self.dev_str.clone();
+            let dev_type = self.dev_type;
+
+            let state = self.session.run(move |mainloop, context| {
+                let state = Arc::new(Mutex::new(None));
+                let clone = Arc::clone(&state);
+                let error = Arc::new(Mutex::new(None));
+                let err_clone = error.clone();
+
+                match dev_type {
+                    DeviceType::Input => {
+                        let op = context.introspect().get_source_info_list( move |info | {
+                                match info {
+                                    libpulse_binding::callbacks::ListResult::Item(device) => {
+                                        if let Some(name) = &device.name && name.to_string() == dev_str {
+                                            clone.lock().unwrap().replace(DeviceState {
+                                                index: device.index,
+                                                volume: device.volume,
+                                                channel_map: device.channel_map,
+                                                mute: device.mute,
+                                                ports: device.ports.iter()
+                                                    .map(|port| to_port(&port.name, &port.description, port.priority, port.available))
+                                                    .collect(),
+                                                active_port: device.active_port.as_ref().and_then(|port| port.name.as_ref().map(|name| name.to_string())),
+                                                base_volume: device.base_volume,
+                                                hw_db_volume: device.flags.contains(SourceFlagSet::HW_VOLUME_CTRL | SourceFlagSet::DECIBEL_VOLUME),
+                                                info: DeviceInfo {
+                                                    driver: device.driver.as_ref().map(|driver| driver.to_string()),
+                                                    hw_volume: Some(device.flags.contains(SourceFlagSet::HW_VOLUME_CTRL)),
+                                                    db_volume: Some(device.flags.contains(SourceFlagSet::DECIBEL_VOLUME)),
+                                                    latency: Some(device.flags.contains(SourceFlagSet::LATENCY)),
+                                                    volume_steps: Some(device.n_volume_steps),
+                                                    card: device.card,
+                                                    ..to_info(&device.sample_spec, &device.channel_map, &device.proplist, device.base_volume)
+                                                },
+                                            });
+                                        }
+                                    },
+                                    libpulse_binding::callbacks::ListResult::End => {
+                                        debug_println("Devices finished")
+                                    },
+                                    libpulse_binding::callbacks::ListResult::Error => {
+                                        err_clone.lock().unwrap().replace(Error::VolumeCaptureFailed(format!("ListResult Access Error"))); 
+                                        debug_eprintln("error gathering device information");
+                                    },
+                                }
+                            });
+                        pulseaudio::wait_for_operation(mainloop, &op);
+                    },
+                    _ => {
+                        let op = context.introspect().get_sink_info_list( move |info | {
+                                match info {
+                                    libpulse_binding::callbacks::ListResult::Item(device) => {
+                                        if let Some(name) = &device.name && name.to_string() == dev_str {
+                                            clone.lock().unwrap().replace(DeviceState {
+                                                index: device.index,
+                                                volume: device.volume,
+                                                channel_map: device.channel_map,
+                                                mute: device.mute,
+                                                ports: device.ports.iter()
+                                                    .map(|port| to_port(&port.name, &port.description, port.priority, port.available))
+                                                    .collect(),
+                                                active_port: device.active_port.as_ref().and_then(|port| port.name.as_ref().map(|name| name.to_string())),
+                                                base_volume: device.base_volume,
+                                                hw_db_volume: device.flags.contains(SinkFlagSet::HW_VOLUME_CTRL | SinkFlagSet::DECIBEL_VOLUME),
+                                                info: DeviceInfo {
+                                                    driver: device.driver.as_ref().map(|driver| driver.to_string()),
+                                                    hw_volume: Some(device.flags.contains(SinkFlagSet::HW_VOLUME_CTRL)),
+                                                    db_volume: Some(device.flags.contains(SinkFlagSet::DECIBEL_VOLUME)),
+                                                    latency: Some(device.flags.contains(SinkFlagSet::LATENCY)),
+                                                    volume_steps: Some(device.n_volume_steps),
+                                                    card: device.card,
+                                                    ..to_info(&device.sample_spec, &device.channel_map, &device.proplist, device.base_volume)
+                                                },
+                                            });
+                                        }
+                                    },
+                                    libpulse_binding::callbacks::ListResult::End => {
+                                        debug_println("Devices finished")
+                                    },
+                                    libpulse_binding::callbacks::ListResult::Error => {
+                                        err_clone.lock().unwrap().replace(Error::VolumeCaptureFailed(format!("ListResult Access Error"))); 
+                                        debug_eprintln("error gathering device information");
+                                    },
+                                }
+                            });
+                        pulseaudio::wait_for_operation(mainloop, &op);
+                    },
+                }
 
-            if let Some(index) = dev_index.lock().unwrap().take() {
-                let mute_runner;
-                mute_runner = context.introspect().set_sink_mute_by_index(index, mute, None);        
-                while mute_runner.get_state() == libpulse_binding::operation::State::Running {
-                    mainloop.iterate(false);
-                    status = true;
+                if let Some(error) = error.lock().unwrap().take() {
+                    return Err(error);
                 }
-            } else {
-                status = false;
+                let state = state.lock().unwrap().take();
+                Ok(state)
+            })??;
+
+            match state {
+                Some(state) => Ok(state),
+                None => Err(Error::VolumeCaptureFailed(format!("Failed to detect device"))),
             }
-            mainloop.quit(libpulse_binding::def::Retval(0));
-            if let Some(error) = error.lock().unwrap().take() {
-                Err(error)
-            } else if !*changed.lock().unwrap() {
-                Err(Error::VolumeCaptureFailed(format!("Failed to detect device")))
+        }
+
+        fn apply_volume(&self, index: u32, volume: ChannelVolumes) -> Result<(), Error> {
+            let dev_type = self.dev_type;
+            self.record_change(ChangeKind::Volume);
+            let success = self.session.run(move |mainloop, context| {
+                let success = Arc::new(Mutex::new(false));
+                let clone = Arc::clone(&success);
+                let callback = Some(Box::new(move |status| *clone.lock().unwrap() = status) as Box<dyn FnMut(bool)>);
+                let op = match dev_type {
+                    DeviceType::Input => context.introspect().set_source_volume_by_index(index, &volume, callback),
+                    _ => context.introspect().set_sink_volume_by_index(index, &volume, callback),
+                };
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let success = *success.lock().unwrap();
+                success
+            })?;
+
+            if success {
+                Ok(())
             } else {
-                match status {
-                    true => {
-                        Ok(())
-                    }
-                    false => {
-                        Err(Error::VolumeCaptureFailed(format!("Failed to adjust device volume")))
-                    }
-                }
+                Err(Error::VolumeSetFailed(format!("Failed to adjust device volume")))
             }
-        } 
-    }
+        }
 
-    impl PulseAudioDevice {
-        pub fn get_device_str(&self) -> String {
-            self.dev_str.clone()
+        fn apply_mute(&self, index: u32, mute: bool) -> Result<(), Error> {
+            let dev_type = self.dev_type;
+            self.record_change(ChangeKind::Mute);
+            let success = self.session.run(move |mainloop, context| {
+                let success = Arc::new(Mutex::new(false));
+                let clone = Arc::clone(&success);
+                let callback = Some(Box::new(move |status| *clone.lock().unwrap() = status) as Box<dyn FnMut(bool)>);
+                let op = match dev_type {
+                    DeviceType::Input => context.introspect().set_source_mute_by_index(index, mute, callback),
+                    _ => context.introspect().set_sink_mute_by_index(index, mute, callback),
+                };
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let success = *success.lock().unwrap();
+                success
+            })?;
+
+            if success {
+                Ok(())
+            } else {
+                Err(Error::MuteSetFailed(format!("Failed to adjust device mute")))
+            }
         }
     }
     
@@ -1,132 +1,89 @@
+pub mod card;
 pub mod device;
+pub mod options;
+pub mod session;
+pub mod stream;
+pub mod subscribe;
 
 // #[cfg(not(target_os="linux"))]
 #[cfg(target_os="linux")]
-// Currently no functionality to detect jacks, only output audio cards
+// Output sinks and input sources, jack detection is exposed through device ports
 pub mod pulseaudio {
     use libpulse_binding::{
-        context::{Context, introspect::SinkInfo}, 
-        callbacks::ListResult,
-        mainloop::standard::Mainloop,
-        proplist::Proplist
+        context::{Context, FlagSet},
+        error::{Code, PAErr},
+        mainloop::standard::{IterateResult, Mainloop},
+        operation::Operation,
+        proplist::{Proplist, properties},
     };
-    use std::sync::{Arc, Mutex};
-    use crate::{VolumeControl, debug_eprintln, debug_println, device::DeviceTrait, error::Error, pulseaudio::device::PulseAudioDevice};
+    use crate::{debug_eprintln, device::DeviceTrait, error::Error, pulseaudio::{device::PulseAudioDevice, options::{ClientIdentity, ConnectionOptions}, session::PulseSession}};
 
     pub fn get_device_identifiers() -> Result<Vec<(String, String)>, Error> {
-        let mut devices: Vec<(String, String)> = Vec::new();
-        
-        let device_list = Arc::new(Mutex::new(Vec::new()));
-        let mut mainloop = Mainloop::new().expect("Failed to create mainloop");
-        let proplist = Proplist::new().unwrap();
-        let mut context = Context::new_with_proplist(&mainloop, "CPVC", &proplist)
-            .expect("Failed to create connection context");
-
-
-        context.connect(None, libpulse_binding::context::FlagSet::NOFLAGS, None)
-            .expect("Failed to connect context");
-
-        loop {
-            match context.get_state() {
-                libpulse_binding::context::State::Ready => break,
-                libpulse_binding::context::State::Failed | libpulse_binding::context::State::Terminated => {
-                    panic!("Context failed or terminated");
-                }
-                _ => {
-                    mainloop.iterate(false);
-                }
-            }
-        }
-        let clone = Arc::clone(&device_list);
-        let error = Arc::new(Mutex::new(None));
-        let error_clone = error.clone();
-        let op = context.introspect().get_sink_info_list(move |info: ListResult<&SinkInfo> | {
-            match info {
-                libpulse_binding::callbacks::ListResult::Item(device) => {
-                    if let Some(description) = device.description.as_ref() && let Some(name) = device.name.as_ref() {
-                        if let Ok(mut lock) = clone.lock() {
-                            lock.push((name.to_string(), description.to_string()));
-                        } else {
-                            error_clone.lock().unwrap().replace(Some(Error::DeviceAccessFailed(format!("Failed to unlock device list on device {}", name))));
-                        }
-                    } else {
-                        error_clone.lock().unwrap().replace(Some(Error::DeviceAccessFailed(format!("Failed to access device description"))));
-                    }
-                },
-                libpulse_binding::callbacks::ListResult::End => {
-                    debug_println("Devices finished");
-                },
-                libpulse_binding::callbacks::ListResult::Error => {
-                    debug_eprintln("error gathering device information");
-                },
+        PulseSession::shared()?.get_device_identifiers()
+    }
+
+    // Sources are input devices (e.g. microphones), monitor sources mirror the output of a sink
+    pub fn get_source_identifiers(include_monitors: bool) -> Result<Vec<(String, String)>, Error> {
+        PulseSession::shared()?.get_source_identifiers(include_monitors)
+    }
+
+    // Some calls return the negated code, `pa_strerror` accepts both
+    pub(super) fn to_error(error: PAErr) -> Error {
+        Error::PulseAudio(error.0.abs(), format!("{}", error))
+    }
+
+    // Errors of a server that went away or stopped answering rather than of a rejected request
+    pub(crate) fn is_connection_error(error: &Error) -> bool {
+        let lost = [Code::ConnectionRefused, Code::ConnectionTerminated, Code::Timeout, Code::Killed];
+        matches!(error, Error::PulseAudio(code, _) if lost.iter().any(|lost| *lost as i32 == *code))
+    }
+
+    // Application properties the server shows for the client
+    pub(super) fn to_proplist(identity: &ClientIdentity) -> Result<Proplist, Error> {
+        let mut proplist = Proplist::new()
+            .ok_or(Error::DeviceAccessFailed(format!("Failed to create proplist")))?;
+        let entries = [
+            (properties::APPLICATION_NAME, Some(identity.get_name())),
+            (properties::APPLICATION_ID, identity.id.clone()),
+            (properties::APPLICATION_ICON_NAME, identity.icon_name.clone()),
+            (properties::APPLICATION_VERSION, identity.version.clone()),
+        ];
+        for (key, value) in entries {
+            if let Some(value) = value {
+                proplist.set_str(key, &value)
+                    .map_err(|_| Error::DeviceAccessFailed(format!("Failed to set {} to {}", key, value)))?;
             }
-        });
-        
-        while op.get_state() == libpulse_binding::operation::State::Running {
-            mainloop.iterate(false);
         }
+        Ok(proplist)
+    }
 
-        mainloop.quit(libpulse_binding::def::Retval(0));
-
-        if let Ok(list) = &mut device_list.lock() {
-            devices.append(list);
-        } else {
-            return Err(Error::DeviceEnumerationFailed(format!("Failed to lock onto device list")));
+    // Starts connecting to the server picked by `options`, the caller waits for the context to become ready
+    pub(super) fn connect_context(context: &mut Context, options: &ConnectionOptions) -> Result<(), Error> {
+        if let Some(cookie) = options.get_cookie() {
+            context.load_cookie_from_file(&cookie.to_string_lossy()).map_err(to_error)?;
         }
-        Ok(devices)
-    }
-
-    pub(super) fn acquire_mainloop_and_context() -> (Mainloop, Context) {
-        let mut mainloop = Mainloop::new().expect("Failed to create mainloop");
-        let proplist = Proplist::new().unwrap();
-        let mut context = Context::new_with_proplist(&mainloop, "CPVC", &proplist)
-            .expect("Failed to create connection context");
-        
-        context.connect(None, libpulse_binding::context::FlagSet::NOFLAGS, None)
-            .expect("Failed to connect context");
-
-        loop {
-            match context.get_state() {
-                libpulse_binding::context::State::Ready => break,
-                libpulse_binding::context::State::Failed | libpulse_binding::context::State::Terminated => {
-                    panic!("Context failed or terminated");
-                }
-                _ => {
-                    mainloop.iterate(false);
+        context.connect(options.get_server().as_deref(), FlagSet::NOFLAGS, None)
+            .map_err(to_error)
+    }
+
+    pub(super) fn wait_for_operation<T: ?Sized>(mainloop: &mut Mainloop, op: &Operation<T>) {
+        while op.get_state() == libpulse_binding::operation::State::Running {
+            match mainloop.iterate(true) {
+                IterateResult::Success(_) => {},
+                IterateResult::Quit(_) | IterateResult::Err(_) => {
+                    debug_eprintln("mainloop stopped before operation finished");
+                    break;
                 }
             }
         }
-
-        (mainloop, context)
     }
 
-    // Default output device does not work when Pro Audio is selected as a playback device
     pub fn get_default_output_dev() -> Result<PulseAudioDevice, Error> {
-        let default_dev = Arc::new(Mutex::new(String::new()));
-        let clone = Arc::clone(&default_dev);
-
-        let (mut mainloop, context) = acquire_mainloop_and_context();
-        let op = context.introspect().get_sink_info_list( move |info | {
-                match info {
-                    libpulse_binding::callbacks::ListResult::Item(device) => {
-                        if let Some(_) = device.active_port {
-                            *clone.lock().unwrap() = device.description.as_ref().unwrap().to_string();
-                        }
-                    },
-                    libpulse_binding::callbacks::ListResult::End => {
-                        debug_println("Devices finished")
-                    },
-                    libpulse_binding::callbacks::ListResult::Error => {
-                        debug_eprintln("error gathering device information");
-                    },
-                }
-            });
-        while op.get_state() == libpulse_binding::operation::State::Running {
-            mainloop.iterate(false);
-        }
-        mainloop.quit(libpulse_binding::def::Retval(0));
-        let device_name = default_dev.lock().unwrap().clone();
-        PulseAudioDevice::from_name(device_name)
+        PulseSession::shared()?.get_default_output_dev()
+    }
+
+    pub fn get_default_input_dev() -> Result<PulseAudioDevice, Error> {
+        PulseSession::shared()?.get_default_input_dev()
     }
 
     pub fn get_device_id(name: String) -> Result<String, Error> {
@@ -149,41 +106,19 @@ pub mod pulseaudio {
         Err(Error::DeviceNotFound)
     }
 
+    pub fn get_input_device_name(id: String) -> Result<String, Error> {
+        let devices = get_source_identifiers(true)?;
+        for (dev_str, name) in devices {
+            if id == dev_str {
+                return Ok(name);
+            }
+        }
+        Err(Error::DeviceNotFound)
+    }
+
     // Add Alsa ID to PulseAudio?
     pub fn convert_alsa_id(alsa_card: String, alsa_id: String) -> Result<String, Error> {
-        let possible_device = Arc::new(Mutex::new(String::new()));
-        let clone = Arc::clone(&possible_device);
-
-        let (mut mainloop, context) = acquire_mainloop_and_context();
-        let op = context.introspect().get_sink_info_list( move |info | {
-                match info {
-                    libpulse_binding::callbacks::ListResult::Item(device) => {
-                        let properties = &device.proplist;
-                        let alsa_card_out = properties.get_str("alsa.card");
-                        let alsa_device = properties.get_str("alsa.device");
-
-                        if alsa_card_out.as_ref() == Some(&alsa_card) && alsa_device.as_ref() == Some(&alsa_id) {
-                            *clone.lock().unwrap() = device.name.as_ref().unwrap().to_string();
-                        }
-                    },
-                    libpulse_binding::callbacks::ListResult::End => {
-                        debug_println("Devices finished")
-                    },
-                    libpulse_binding::callbacks::ListResult::Error => {
-                        debug_eprintln("error gathering device information");
-                    },
-                }
-            });
-        while op.get_state() == libpulse_binding::operation::State::Running {
-            mainloop.iterate(false);
-        }
-        mainloop.quit(libpulse_binding::def::Retval(0));
-        let device_name = possible_device.lock().unwrap().clone();
-        if device_name != "" {
-            Ok(device_name)
-        } else {
-            Err(Error::DeviceNotFound)
-        }
+        PulseSession::shared()?.convert_alsa_id(alsa_card, alsa_id)
     }
 
     // Volume Controls
@@ -191,6 +126,10 @@ pub mod pulseaudio {
         Ok(get_device_identifiers()?.into_iter().map(|(_id, name)| name).collect())
     }
 
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Ok(get_device_identifiers()?.into_iter().map(|(id, _name)| id).collect())
+    }
+
     pub fn get_vol() -> Result<f32, Error> {
         let default_dev = get_default_output_dev()?;
         default_dev.get_vol()
@@ -211,6 +150,35 @@ pub mod pulseaudio {
         default_dev.set_mute(state)
     }
 
+    // Input Controls
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        Ok(get_source_identifiers(false)?.into_iter().map(|(_id, name)| name).collect())
+    }
+
+    pub fn get_input_device_uids() -> Result<Vec<String>, Error> {
+        Ok(get_source_identifiers(false)?.into_iter().map(|(id, _name)| id).collect())
+    }
+
+    pub fn get_input_vol() -> Result<f32, Error> {
+        let default_dev = get_default_input_dev()?;
+        default_dev.get_vol()
+    }
+
+    pub fn set_input_vol(value: f32) -> Result<(), Error> {
+        let default_dev = get_default_input_dev()?;
+        default_dev.set_vol(value)
+    }
+
+    pub fn get_input_mute() -> Result<bool, Error> {
+        let default_dev = get_default_input_dev()?;
+        default_dev.get_mute()
+    }
+
+    pub fn set_input_mute(state: bool) -> Result<(), Error> {
+        let default_dev = get_default_input_dev()?;
+        default_dev.set_mute(state)
+    }
+
     
 }
 
@@ -224,11 +192,18 @@ pub mod pulseaudio {
        Err(Error::PlatformUnsupported)
     }
 
-    // Default output device does not work when Pro Audio is selected as a playback device
     pub fn get_default_output_dev() -> Result<PulseAudioDevice, Error> {
        Err(Error::PlatformUnsupported)
     }
 
+    pub fn get_source_identifiers(include_monitors: bool) -> Result<Vec<(String, String)>, Error> {
+       Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_default_input_dev() -> Result<PulseAudioDevice, Error> {
+       Err(Error::PlatformUnsupported)
+    }
+
     pub fn get_device_id(name: String) -> Result<String, Error> {
         Err(Error::PlatformUnsupported)
     }
@@ -258,6 +233,27 @@ pub mod pulseaudio {
     pub fn set_mute(state: bool) -> Result<(), Error> {
         Err(Error::PlatformUnsupported)
     }
+
+    // Input Controls
+    pub fn get_input_devices() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_input_vol() -> Result<f32, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn set_input_vol(value: f32) -> Result<(), Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn get_input_mute() -> Result<bool, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+    pub fn set_input_mute(state: bool) -> Result<(), Error> {
+        Err(Error::PlatformUnsupported)
+    }
     
 }
 
@@ -0,0 +1,137 @@
+use std::{env, path::{Path, PathBuf}};
+
+/// Client name used when `ClientIdentity::name` is unset
+pub const DEFAULT_CLIENT_NAME: &str = "CPVC";
+
+/// How cpvc introduces itself to the server, shown in client lists such as pavucontrol's and in event origins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClientIdentity {
+    /// `application.name`, `DEFAULT_CLIENT_NAME` when unset
+    pub name: Option<String>,
+    /// `application.id`, usually reverse DNS (e.g. `org.example.Mixer`)
+    pub id: Option<String>,
+    /// `application.icon_name`, an XDG icon name
+    pub icon_name: Option<String>,
+    /// `application.version`
+    pub version: Option<String>,
+}
+
+impl ClientIdentity {
+    pub fn new(name: &str) -> Self {
+        ClientIdentity {
+            name: Some(name.to_string()),
+            ..ClientIdentity::default()
+        }
+    }
+
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    pub fn with_icon_name(mut self, icon_name: &str) -> Self {
+        self.icon_name = Some(icon_name.to_string());
+        self
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    pub fn get_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| DEFAULT_CLIENT_NAME.to_string())
+    }
+}
+
+/// Server a PulseAudio connection is made to
+///
+/// Unset fields fall back to `PULSE_SERVER` and `PULSE_COOKIE`, then to libpulse's own discovery
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionOptions {
+    /// Address in libpulse syntax (e.g. `unix:/run/user/1000/pulse/native`, `tcp:host:4713`),
+    /// a bare path to a directory is taken as a runtime directory
+    pub server: Option<String>,
+    /// Authentication cookie, needed for servers of other users and over TCP
+    pub cookie: Option<PathBuf>,
+    pub identity: ClientIdentity,
+}
+
+impl ConnectionOptions {
+    /// Unix socket of the server, usually `pulse/native` in a runtime directory
+    pub fn socket(path: impl AsRef<Path>) -> Self {
+        ConnectionOptions {
+            server: Some(format!("unix:{}", path.as_ref().display())),
+            ..ConnectionOptions::default()
+        }
+    }
+
+    pub fn tcp(host: &str, port: u16) -> Self {
+        // IPv6 addresses need brackets to keep the port apart
+        let host = if host.contains(':') && !host.starts_with('[') { format!("[{}]", host) } else { host.to_string() };
+        ConnectionOptions {
+            server: Some(format!("tcp:{}:{}", host, port)),
+            ..ConnectionOptions::default()
+        }
+    }
+
+    /// Server of the user owning `path` (e.g. `/run/user/1000`), the way a system service reaches a logged in session
+    pub fn runtime_dir(path: impl AsRef<Path>) -> Self {
+        ConnectionOptions::socket(path.as_ref().join("pulse").join("native"))
+    }
+
+    pub fn with_cookie(mut self, path: impl AsRef<Path>) -> Self {
+        self.cookie = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_identity(mut self, identity: ClientIdentity) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    /// Address handed to libpulse, `None` leaves the choice to libpulse
+    pub fn get_server(&self) -> Option<String> {
+        let server = self.server.clone().or_else(|| env::var("PULSE_SERVER").ok().filter(|server| !server.is_empty()))?;
+        if !server.contains(':') && Path::new(&server).is_dir() {
+            return ConnectionOptions::runtime_dir(&server).server;
+        }
+        Some(server)
+    }
+
+    pub fn get_cookie(&self) -> Option<PathBuf> {
+        self.cookie.clone().or_else(|| env::var_os("PULSE_COOKIE").filter(|cookie| !cookie.is_empty()).map(PathBuf::from))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{ClientIdentity, ConnectionOptions};
+
+    #[test]
+    fn test_connection_options() {
+        assert_eq!(ConnectionOptions::tcp("10.0.0.2", 4713).get_server().as_deref(), Some("tcp:10.0.0.2:4713"));
+        assert_eq!(ConnectionOptions::tcp("::1", 4713).get_server().as_deref(), Some("tcp:[::1]:4713"));
+        assert_eq!(ConnectionOptions::runtime_dir("/run/user/1000").get_server().as_deref(), Some("unix:/run/user/1000/pulse/native"));
+
+        let runtime_dir = std::env::temp_dir();
+        let options = ConnectionOptions {
+            server: Some(runtime_dir.display().to_string()),
+            ..ConnectionOptions::default()
+        }.with_cookie("/home/user/.config/pulse/cookie");
+        assert_eq!(options.get_server(), Some(format!("unix:{}", runtime_dir.join("pulse").join("native").display())));
+        assert_eq!(options.get_cookie().as_deref(), Some(std::path::Path::new("/home/user/.config/pulse/cookie")));
+    }
+
+    #[test]
+    fn test_client_identity() {
+        assert_eq!(ClientIdentity::default().get_name(), "CPVC");
+        let identity = ClientIdentity::new("Mixer").with_id("org.example.Mixer").with_version("1.2.0");
+        assert_eq!(identity.get_name(), "Mixer");
+        assert_eq!(identity.id.as_deref(), Some("org.example.Mixer"));
+        assert_eq!(identity.icon_name, None);
+        assert_eq!(ConnectionOptions::default().with_identity(identity.clone()).identity, identity);
+    }
+}
@@ -0,0 +1,403 @@
+// #[cfg(not(target_os="linux"))]
+#[cfg(target_os="linux")]
+
+mod session {
+
+    use std::{sync::{Arc, Mutex, mpsc::{self, Sender}}, thread};
+    use libpulse_binding::{
+        callbacks::ListResult,
+        context::{Context, introspect::{SinkInfo, SourceInfo}},
+        mainloop::standard::Mainloop,
+    };
+    use crate::{DeviceType, debug_eprintln, debug_println, error::Error, pulseaudio::{self, device::PulseAudioDevice, options::{ClientIdentity, ConnectionOptions}}};
+
+    type Job = Box<dyn FnOnce(Result<&mut Connection, Error>) + Send>;
+
+    struct Connection {
+        mainloop: Mainloop,
+        context: Context,
+    }
+
+    impl Connection {
+        fn connect(options: &ConnectionOptions) -> Result<Self, Error> {
+            let mut mainloop = Mainloop::new()
+                .ok_or(Error::DeviceAccessFailed(format!("Failed to create mainloop")))?;
+            let proplist = pulseaudio::to_proplist(&options.identity)?;
+            let mut context = Context::new_with_proplist(&mainloop, &options.identity.get_name(), &proplist)
+                .ok_or(Error::DeviceAccessFailed(format!("Failed to create connection context")))?;
+
+            pulseaudio::connect_context(&mut context, options)?;
+
+            loop {
+                match context.get_state() {
+                    libpulse_binding::context::State::Ready => break,
+                    libpulse_binding::context::State::Failed | libpulse_binding::context::State::Terminated => {
+                        return Err(pulseaudio::to_error(context.errno()));
+                    }
+                    _ => {
+                        mainloop.iterate(true);
+                    }
+                }
+            }
+
+            Ok(Connection {
+                mainloop,
+                context,
+            })
+        }
+
+        fn is_ready(&self) -> bool {
+            self.context.get_state() == libpulse_binding::context::State::Ready
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            self.context.disconnect();
+            self.mainloop.quit(libpulse_binding::def::Retval(0));
+        }
+    }
+
+    /// A single PulseAudio client connection shared by every device created from it
+    ///
+    /// The connection lives on a worker thread which runs requests one after another,
+    /// it is re-established automatically if the server goes away
+    #[derive(Debug)]
+    pub struct PulseSession {
+        jobs: Sender<Job>,
+        options: ConnectionOptions,
+    }
+
+    static SHARED_SESSION: Mutex<Option<Arc<PulseSession>>> = Mutex::new(None);
+    static SHARED_OPTIONS: Mutex<ConnectionOptions> = Mutex::new(ConnectionOptions {
+        server: None,
+        cookie: None,
+        identity: ClientIdentity { name: None, id: None, icon_name: None, version: None },
+    });
+
+    /// Sets the server of the shared session, the current shared connection is closed once its devices are dropped
+    pub fn set_shared_options(options: ConnectionOptions) {
+        *SHARED_OPTIONS.lock().unwrap() = options;
+        SHARED_SESSION.lock().unwrap().take();
+    }
+
+    pub fn get_shared_options() -> ConnectionOptions {
+        SHARED_OPTIONS.lock().unwrap().clone()
+    }
+
+    // Devices keep the old session, the next `PulseSession::shared` call connects again
+    pub(crate) fn close_shared_session() {
+        SHARED_SESSION.lock().unwrap().take();
+    }
+
+    impl PulseSession {
+        /// Opens a new connection to the server of the shared session
+        pub fn new() -> Result<Arc<Self>, Error> {
+            PulseSession::connect(get_shared_options())
+        }
+
+        /// Opens a new connection to the server picked by `options`
+        pub fn connect(options: ConnectionOptions) -> Result<Arc<Self>, Error> {
+            let (jobs, receiver) = mpsc::channel::<Job>();
+            let (setup_tx, setup_rx) = mpsc::channel();
+            let thread_options = options.clone();
+
+            thread::Builder::new().name("cpvc-pulse-session".to_string()).spawn(move || {
+                let mut connection = match Connection::connect(&thread_options) {
+                    Ok(connection) => {
+                        let _ = setup_tx.send(Ok(()));
+                        Some(connection)
+                    },
+                    Err(error) => {
+                        let _ = setup_tx.send(Err(error));
+                        return;
+                    }
+                };
+
+                // Runs until every handle to the session is dropped
+                for job in receiver {
+                    if !connection.as_ref().is_some_and(|connection| connection.is_ready()) {
+                        debug_eprintln("PulseAudio connection lost, reconnecting");
+                        connection = None;
+                        match Connection::connect(&thread_options) {
+                            Ok(new_connection) => {
+                                connection = Some(new_connection);
+                            },
+                            Err(error) => {
+                                job(Err(error));
+                                continue;
+                            }
+                        }
+                    }
+                    if let Some(connection) = connection.as_mut() {
+                        job(Ok(connection));
+                    }
+                }
+            }).map_err(|e| Error::DeviceAccessFailed(format!("Failed to spawn session thread {}", e)))?;
+
+            match setup_rx.recv() {
+                Ok(Ok(())) => Ok(Arc::new(PulseSession { jobs, options })),
+                Ok(Err(error)) => Err(error),
+                Err(_) => Err(Error::DeviceAccessFailed(format!("Session thread exited unexpectedly"))),
+            }
+        }
+
+        /// Session used by the crate level functions and devices created without a session
+        ///
+        /// Connects on first use
+        pub fn shared() -> Result<Arc<Self>, Error> {
+            let mut shared = SHARED_SESSION.lock().unwrap();
+            if let Some(session) = shared.as_ref() {
+                return Ok(Arc::clone(session));
+            }
+            let session = PulseSession::new()?;
+            shared.replace(Arc::clone(&session));
+            Ok(session)
+        }
+
+        pub fn get_options(&self) -> &ConnectionOptions {
+            &self.options
+        }
+
+        /// Runs `job` on the session thread with the connected mainloop and context
+        ///
+        /// Jobs must not call back into the session, they would wait on themselves
+        pub(crate) fn run<R, F>(&self, job: F) -> Result<R, Error>
+        where
+            F: FnOnce(&mut Mainloop, &mut Context) -> R + Send + 'static,
+            R: Send + 'static,
+        {
+            let (result_tx, result_rx) = mpsc::channel();
+            self.jobs.send(Box::new(move |connection: Result<&mut Connection, Error>| {
+                let result = connection.map(|connection| job(&mut connection.mainloop, &mut connection.context));
+                let _ = result_tx.send(result);
+            })).map_err(|_| Error::DeviceAccessFailed(format!("PulseAudio session closed")))?;
+
+            result_rx.recv()
+                .map_err(|_| Error::DeviceAccessFailed(format!("PulseAudio session closed")))?
+        }
+
+        pub fn get_device_identifiers(&self) -> Result<Vec<(String, String)>, Error> {
+            self.run(|mainloop, context| {
+                let device_list = Arc::new(Mutex::new(Vec::new()));
+                let clone = Arc::clone(&device_list);
+                let error = Arc::new(Mutex::new(None));
+                let error_clone = error.clone();
+                let failed = Arc::new(Mutex::new(false));
+                let failed_clone = Arc::clone(&failed);
+                let op = context.introspect().get_sink_info_list(move |info: ListResult<&SinkInfo> | {
+                    match info {
+                        libpulse_binding::callbacks::ListResult::Item(device) => {
+                            if let Some(description) = device.description.as_ref() && let Some(name) = device.name.as_ref() {
+                                if let Ok(mut lock) = clone.lock() {
+                                    lock.push((name.to_string(), description.to_string()));
+                                } else {
+                                    error_clone.lock().unwrap().replace(Error::DeviceAccessFailed(format!("Failed to unlock device list on device {}", name)));
+                                }
+                            } else {
+                                error_clone.lock().unwrap().replace(Error::DeviceAccessFailed(format!("Failed to access device description")));
+                            }
+                        },
+                        libpulse_binding::callbacks::ListResult::End => {
+                            debug_println("Devices finished");
+                        },
+                        libpulse_binding::callbacks::ListResult::Error => {
+                            *failed_clone.lock().unwrap() = true;
+                            debug_eprintln("error gathering device information");
+                        },
+                    }
+                });
+                pulseaudio::wait_for_operation(mainloop, &op);
+
+                // A failed listing is not an empty one
+                if *failed.lock().unwrap() {
+                    return Err(pulseaudio::to_error(context.errno()));
+                }
+                if let Some(error) = error.lock().unwrap().take() {
+                    return Err(error);
+                }
+                match device_list.lock() {
+                    Ok(mut list) => Ok(std::mem::take(&mut *list)),
+                    Err(_) => Err(Error::DeviceEnumerationFailed(format!("Failed to lock onto device list"))),
+                }
+            })?
+        }
+
+        // Sources are input devices (e.g. microphones), monitor sources mirror the output of a sink
+        pub fn get_source_identifiers(&self, include_monitors: bool) -> Result<Vec<(String, String)>, Error> {
+            self.run(move |mainloop, context| {
+                let device_list = Arc::new(Mutex::new(Vec::new()));
+                let clone = Arc::clone(&device_list);
+                let error = Arc::new(Mutex::new(None));
+                let error_clone = error.clone();
+                let failed = Arc::new(Mutex::new(false));
+                let failed_clone = Arc::clone(&failed);
+
+                let op = context.introspect().get_source_info_list(move |info: ListResult<&SourceInfo> | {
+                    match info {
+                        libpulse_binding::callbacks::ListResult::Item(device) => {
+                            if device.monitor_of_sink.is_some() && !include_monitors {
+                                return;
+                            }
+                            if let Some(description) = device.description.as_ref() && let Some(name) = device.name.as_ref() {
+                                clone.lock().unwrap().push((name.to_string(), description.to_string()));
+                            } else {
+                                error_clone.lock().unwrap().replace(Error::DeviceAccessFailed(format!("Failed to access device description")));
+                            }
+                        },
+                        libpulse_binding::callbacks::ListResult::End => {
+                            debug_println("Devices finished");
+                        },
+                        libpulse_binding::callbacks::ListResult::Error => {
+                            *failed_clone.lock().unwrap() = true;
+                            debug_eprintln("error gathering device information");
+                        },
+                    }
+                });
+                pulseaudio::wait_for_operation(mainloop, &op);
+
+                if *failed.lock().unwrap() {
+                    return Err(pulseaudio::to_error(context.errno()));
+                }
+                if let Some(error) = error.lock().unwrap().take() {
+                    return Err(error);
+                }
+                Ok(std::mem::take(&mut *device_list.lock().unwrap()))
+            })?
+        }
+
+        // UID is device name (e.g. "auto_null" or "auto_null.monitor")
+        // Sinks are matched before sources
+        pub fn get_device(self: &Arc<Self>, uid: String) -> Result<PulseAudioDevice, Error> {
+            for (dev_str, _name) in self.get_device_identifiers()? {
+                if uid == dev_str {
+                    return Ok(PulseAudioDevice::new(dev_str, DeviceType::Output, Arc::clone(self)));
+                }
+            }
+            for (dev_str, _name) in self.get_source_identifiers(true)? {
+                if uid == dev_str {
+                    return Ok(PulseAudioDevice::new(dev_str, DeviceType::Input, Arc::clone(self)));
+                }
+            }
+            Err(Error::DeviceNotFound)
+        }
+
+        // Name is device description (e.g. "Dummy Output")
+        pub fn get_device_by_name(self: &Arc<Self>, name: String) -> Result<PulseAudioDevice, Error> {
+            for (dev_str, names) in self.get_device_identifiers()? {
+                if name == names {
+                    return Ok(PulseAudioDevice::new(dev_str, DeviceType::Output, Arc::clone(self)));
+                }
+            }
+            for (dev_str, names) in self.get_source_identifiers(true)? {
+                if name == names {
+                    return Ok(PulseAudioDevice::new(dev_str, DeviceType::Input, Arc::clone(self)));
+                }
+            }
+            Err(Error::DeviceNotFound)
+        }
+
+        pub fn get_default_output_dev(self: &Arc<Self>) -> Result<PulseAudioDevice, Error> {
+            let (default_sink, _default_source) = self.get_default_names()?;
+            match default_sink {
+                Some(dev_str) => self.get_device(dev_str),
+                None => Err(Error::DeviceNotFound),
+            }
+        }
+
+        pub fn get_default_input_dev(self: &Arc<Self>) -> Result<PulseAudioDevice, Error> {
+            let (_default_sink, default_source) = self.get_default_names()?;
+            match default_source {
+                Some(dev_str) => self.get_device(dev_str),
+                None => Err(Error::DeviceNotFound),
+            }
+        }
+
+        // Names of the default sink and source set on the server
+        fn get_default_names(&self) -> Result<(Option<String>, Option<String>), Error> {
+            self.run(|mainloop, context| {
+                let default_devs = Arc::new(Mutex::new((None, None)));
+                let clone = Arc::clone(&default_devs);
+                let op = context.introspect().get_server_info(move |info| {
+                    *clone.lock().unwrap() = (
+                        info.default_sink_name.as_ref().map(|name| name.to_string()),
+                        info.default_source_name.as_ref().map(|name| name.to_string()),
+                    );
+                });
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let default_devs = default_devs.lock().unwrap().clone();
+                default_devs
+            })
+        }
+
+        // Add Alsa ID to PulseAudio?
+        pub fn convert_alsa_id(&self, alsa_card: String, alsa_id: String) -> Result<String, Error> {
+            let device_name = self.run(|mainloop, context| {
+                let possible_device = Arc::new(Mutex::new(String::new()));
+                let clone = Arc::clone(&possible_device);
+                let op = context.introspect().get_sink_info_list( move |info | {
+                        match info {
+                            libpulse_binding::callbacks::ListResult::Item(device) => {
+                                let properties = &device.proplist;
+                                let alsa_card_out = properties.get_str("alsa.card");
+                                let alsa_device = properties.get_str("alsa.device");
+
+                                if alsa_card_out.as_ref() == Some(&alsa_card) && alsa_device.as_ref() == Some(&alsa_id) && let Some(name) = device.name.as_ref() {
+                                    *clone.lock().unwrap() = name.to_string();
+                                }
+                            },
+                            libpulse_binding::callbacks::ListResult::End => {
+                                debug_println("Devices finished")
+                            },
+                            libpulse_binding::callbacks::ListResult::Error => {
+                                debug_eprintln("error gathering device information");
+                            },
+                        }
+                    });
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let device_name = possible_device.lock().unwrap().clone();
+                device_name
+            })?;
+            if device_name != "" {
+                Ok(device_name)
+            } else {
+                Err(Error::DeviceNotFound)
+            }
+        }
+    }
+
+}
+
+#[cfg(not(target_os="linux"))]
+// #[cfg(target_os="linux")]
+
+mod session {
+
+    use std::sync::Arc;
+    use crate::{error::Error, pulseaudio::options::ConnectionOptions};
+
+    pub struct PulseSession {}
+
+    pub fn set_shared_options(_options: ConnectionOptions) {}
+
+    pub fn get_shared_options() -> ConnectionOptions {
+        ConnectionOptions::default()
+    }
+
+    impl PulseSession {
+        pub fn new() -> Result<Arc<Self>, Error> {
+            Err(Error::PlatformUnsupported)
+        }
+
+        pub fn connect(_options: ConnectionOptions) -> Result<Arc<Self>, Error> {
+            Err(Error::PlatformUnsupported)
+        }
+
+        pub fn shared() -> Result<Arc<Self>, Error> {
+            Err(Error::PlatformUnsupported)
+        }
+    }
+
+}
+
+pub use session::*;
@@ -0,0 +1,321 @@
+// #[cfg(not(target_os="linux"))]
+#[cfg(target_os="linux")]
+
+mod stream {
+
+    use std::{collections::HashMap, sync::{Arc, Mutex}};
+    use libpulse_binding::{
+        context::introspect::SinkInputInfo,
+        proplist::properties,
+        volume::{ChannelVolumes, Volume},
+    };
+    use libpulse_sys::volume::PA_VOLUME_NORM;
+    use crate::{DeviceType, debug_eprintln, debug_println, device::{self, Device}, error::Error, pulseaudio::{self, session::PulseSession}};
+
+    /// A playback stream of an application (PulseAudio sink input)
+    ///
+    /// Stream details are captured when the stream is enumerated,
+    /// volume and mute are always read from the server
+    #[derive(Debug, Clone)]
+    pub struct ApplicationStream {
+        index: u32,
+        name: Option<String>,
+        application_name: Option<String>,
+        binary: Option<String>,
+        pid: Option<u32>,
+        media_role: Option<String>,
+        corked: bool,
+        sink_index: u32,
+        sink_uid: Option<String>,
+        session: Arc<PulseSession>,
+    }
+
+    impl ApplicationStream {
+        fn from_info(info: &SinkInputInfo, session: Arc<PulseSession>) -> Self {
+            let properties = &info.proplist;
+            ApplicationStream {
+                index: info.index,
+                name: info.name.as_ref().map(|name| name.to_string()),
+                application_name: properties.get_str(properties::APPLICATION_NAME),
+                binary: properties.get_str(properties::APPLICATION_PROCESS_BINARY),
+                pid: properties.get_str(properties::APPLICATION_PROCESS_ID).and_then(|pid| pid.parse::<u32>().ok()),
+                media_role: properties.get_str(properties::MEDIA_ROLE),
+                corked: info.corked,
+                sink_index: info.sink,
+                sink_uid: None,
+                session,
+            }
+        }
+    }
+
+    /// Gathers every application stream currently connected to an output device
+    pub fn get_application_streams() -> Result<Vec<ApplicationStream>, Error> {
+        get_session_streams(&PulseSession::shared()?)
+    }
+
+    /// Same as `get_application_streams`, streams keep using `session` for their requests
+    pub fn get_session_streams(session: &Arc<PulseSession>) -> Result<Vec<ApplicationStream>, Error> {
+        let stream_session = Arc::clone(session);
+        let (sink_names, mut streams) = session.run(move |mainloop, context| {
+            let sink_names = Arc::new(Mutex::new(HashMap::new()));
+            let names_clone = Arc::clone(&sink_names);
+            let streams = Arc::new(Mutex::new(Vec::new()));
+            let streams_clone = Arc::clone(&streams);
+
+            let error = Arc::new(Mutex::new(None));
+            let err_clone = error.clone();
+            let err_clone_streams = error.clone();
+
+            let op = context.introspect().get_sink_info_list( move |info | {
+                    match info {
+                        libpulse_binding::callbacks::ListResult::Item(device) => {
+                            if let Some(name) = &device.name {
+                                names_clone.lock().unwrap().insert(device.index, name.to_string());
+                            }
+                        },
+                        libpulse_binding::callbacks::ListResult::End => {
+                            debug_println("Devices finished")
+                        },
+                        libpulse_binding::callbacks::ListResult::Error => {
+                            err_clone.lock().unwrap().replace(Error::DeviceEnumerationFailed(format!("ListResult Access Error")));
+                            debug_eprintln("error gathering device information");
+                        },
+                    }
+                });
+            pulseaudio::wait_for_operation(mainloop, &op);
+
+            let op = context.introspect().get_sink_input_info_list( move |info | {
+                    match info {
+                        libpulse_binding::callbacks::ListResult::Item(stream) => {
+                            streams_clone.lock().unwrap().push(ApplicationStream::from_info(stream, Arc::clone(&stream_session)));
+                        },
+                        libpulse_binding::callbacks::ListResult::End => {
+                            debug_println("Streams finished")
+                        },
+                        libpulse_binding::callbacks::ListResult::Error => {
+                            err_clone_streams.lock().unwrap().replace(Error::DeviceEnumerationFailed(format!("ListResult Access Error")));
+                            debug_eprintln("error gathering stream information");
+                        },
+                    }
+                });
+            pulseaudio::wait_for_operation(mainloop, &op);
+
+            if let Some(error) = error.lock().unwrap().take() {
+                return Err(error);
+            }
+            let sink_names = std::mem::take(&mut *sink_names.lock().unwrap());
+            let streams = std::mem::take(&mut *streams.lock().unwrap());
+            Ok((sink_names, streams))
+        })??;
+
+        for stream in streams.iter_mut() {
+            stream.sink_uid = sink_names.get(&stream.sink_index).cloned();
+        }
+        Ok(streams)
+    }
+
+    impl ApplicationStream {
+        pub fn from_index(index: u32) -> Result<Self, Error> {
+            get_application_streams()?.into_iter()
+                .find(|stream| stream.index == index)
+                .ok_or(Error::DeviceNotFound)
+        }
+
+        pub fn get_index(&self) -> u32 {
+            self.index
+        }
+
+        /// Name of the stream set by the application (e.g. "Playback")
+        pub fn get_name(&self) -> Option<String> {
+            self.name.clone()
+        }
+
+        pub fn get_application_name(&self) -> Option<String> {
+            self.application_name.clone()
+        }
+
+        pub fn get_binary(&self) -> Option<String> {
+            self.binary.clone()
+        }
+
+        pub fn get_pid(&self) -> Option<u32> {
+            self.pid
+        }
+
+        /// Media role of the stream (e.g. "music", "video", "phone")
+        pub fn get_media_role(&self) -> Option<String> {
+            self.media_role.clone()
+        }
+
+        /// A corked stream is paused by its application
+        pub fn is_corked(&self) -> bool {
+            self.corked
+        }
+
+        pub fn get_sink_index(&self) -> u32 {
+            self.sink_index
+        }
+
+        /// UID of the output device the stream plays to
+        pub fn get_sink_uid(&self) -> Option<String> {
+            self.sink_uid.clone()
+        }
+
+        /// Output device the stream plays to, looked up on the server the stream was enumerated from
+        pub fn get_device(&self) -> Result<Device, Error> {
+            let uid = self.sink_uid.clone().ok_or(Error::DeviceNotFound)?;
+            #[cfg(not(feature = "mock"))] {
+                return Ok(Device::from_device(crate::linux::LinuxDevice::PulseAudio(self.session.get_device(uid)?)));
+            }
+            #[cfg(feature = "mock")] {
+                return Device::from_uid(uid);
+            }
+        }
+
+        /// Re-routes the stream to `device`, other streams of the application are left alone
+        pub fn move_to(&mut self, device: &Device) -> Result<(), Error> {
+            if device.get_device_type()? != DeviceType::Output {
+                return Err(Error::DeviceAccessFailed(format!("{} is not an output device", device.get_name()?)));
+            }
+            // Fails early with DeviceNotFound when the stream has ended
+            self.get_state()?;
+
+            let index = self.index;
+            let uid = device.get_uid()?;
+            let sink_uid = uid.clone();
+            let sink_index = self.session.run(move |mainloop, context| {
+                let success = Arc::new(Mutex::new(false));
+                let clone = Arc::clone(&success);
+                let op = context.introspect().move_sink_input_by_name(index, &sink_uid, Some(Box::new(move |status| {
+                    *clone.lock().unwrap() = status;
+                })));
+                pulseaudio::wait_for_operation(mainloop, &op);
+                if !*success.lock().unwrap() {
+                    return Err(Error::DeviceAccessFailed(format!("Failed to move stream {} to {}", index, sink_uid)));
+                }
+
+                let sink_index = Arc::new(Mutex::new(None));
+                let clone = Arc::clone(&sink_index);
+                let op = context.introspect().get_sink_input_info(index, move |info | {
+                        if let libpulse_binding::callbacks::ListResult::Item(stream) = info {
+                            clone.lock().unwrap().replace(stream.sink);
+                        }
+                    });
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let sink_index = sink_index.lock().unwrap().take();
+                Ok(sink_index)
+            })??;
+
+            if let Some(sink_index) = sink_index {
+                self.sink_index = sink_index;
+            }
+            self.sink_uid = Some(uid);
+            Ok(())
+        }
+
+        /// Unlike `Device::get_vol`, a muted stream reports the volume it comes back at once unmuted, see `get_mute`
+        pub fn get_vol(&self) -> Result<f32, Error> {
+            let (volume, _mute) = self.get_state()?;
+            // Loudest channel, the same value devices report
+            Ok(volume.max().0 as f32 / PA_VOLUME_NORM as f32)
+        }
+
+        // Scales every channel so the loudest one matches `value`, keeping the stream's balance
+        // Streams are checked against the boost setting of the device they play to
+        pub fn set_vol(&self, value: f32) -> Result<(), Error> {
+            device::check_vol(self.sink_uid.as_deref().unwrap_or_default(), value)?;
+            let (mut volume, _mute) = self.get_state()?;
+            volume.scale(Volume((value * PA_VOLUME_NORM as f32) as u32))
+                .ok_or(Error::VolumeSetFailed(format!("Failed to scale stream volume")))?;
+
+            let index = self.index;
+            let success = self.session.run(move |mainloop, context| {
+                let success = Arc::new(Mutex::new(false));
+                let clone = Arc::clone(&success);
+                let op = context.introspect().set_sink_input_volume(index, &volume, Some(Box::new(move |status| {
+                    *clone.lock().unwrap() = status;
+                })));
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let success = *success.lock().unwrap();
+                success
+            })?;
+
+            if success {
+                Ok(())
+            } else {
+                Err(Error::VolumeSetFailed(format!("Failed to adjust stream volume")))
+            }
+        }
+
+        pub fn get_mute(&self) -> Result<bool, Error> {
+            let (_volume, mute) = self.get_state()?;
+            Ok(mute)
+        }
+
+        pub fn set_mute(&self, mute: bool) -> Result<(), Error> {
+            let index = self.index;
+            let success = self.session.run(move |mainloop, context| {
+                let success = Arc::new(Mutex::new(false));
+                let clone = Arc::clone(&success);
+                let op = context.introspect().set_sink_input_mute(index, mute, Some(Box::new(move |status| {
+                    *clone.lock().unwrap() = status;
+                })));
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let success = *success.lock().unwrap();
+                success
+            })?;
+
+            if success {
+                Ok(())
+            } else {
+                Err(Error::MuteSetFailed(format!("Failed to adjust stream mute")))
+            }
+        }
+
+        fn get_state(&self) -> Result<(ChannelVolumes, bool), Error> {
+            let index = self.index;
+            let state = self.session.run(move |mainloop, context| {
+                let state = Arc::new(Mutex::new(None));
+                let clone = Arc::clone(&state);
+                let op = context.introspect().get_sink_input_info(index, move |info | {
+                        match info {
+                            libpulse_binding::callbacks::ListResult::Item(stream) => {
+                                clone.lock().unwrap().replace((stream.volume, stream.mute));
+                            },
+                            libpulse_binding::callbacks::ListResult::End => {},
+                            libpulse_binding::callbacks::ListResult::Error => {
+                                debug_eprintln("error gathering stream information");
+                            },
+                        }
+                    });
+                pulseaudio::wait_for_operation(mainloop, &op);
+                let state = state.lock().unwrap().take();
+                state
+            })?;
+
+            match state {
+                Some(state) => Ok(state),
+                None => Err(Error::DeviceNotFound),
+            }
+        }
+    }
+
+}
+
+#[cfg(not(target_os="linux"))]
+// #[cfg(target_os="linux")]
+
+mod stream {
+
+    use crate::error::Error;
+
+    #[derive(Debug, Clone)]
+    pub struct ApplicationStream {}
+
+    pub fn get_application_streams() -> Result<Vec<ApplicationStream>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+}
+
+pub use stream::*;
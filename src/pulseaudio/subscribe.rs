@@ -0,0 +1,388 @@
+// #[cfg(not(target_os="linux"))]
+#[cfg(target_os="linux")]
+
+mod subscribe {
+
+    use std::{collections::HashMap, sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}, mpsc::{self, Sender}}, thread, time::{Duration, Instant}};
+    use libpulse_binding::{
+        callbacks::ListResult,
+        context::{Context, introspect::{Introspector, SinkInfo, SourceInfo}, subscribe::{Facility, InterestMaskSet, Operation}},
+        mainloop::threaded::Mainloop,
+        volume::ChannelVolumes,
+    };
+    use libpulse_sys::volume::PA_VOLUME_NORM;
+    use crate::{DeviceType, curve::{self, VolumeCurve}, debug_eprintln, device::PortAvailability, error::Error, event::{Event, Subscription}, pulseaudio::{self, device::to_availability, options::{ClientIdentity, ConnectionOptions}, session}};
+
+    // Notifications do not say which client made a change, requests of this process are remembered
+    // for a moment and matched against the notifications that follow them
+    const LOCAL_CHANGE_WINDOW: Duration = Duration::from_secs(1);
+
+    static LOCAL_CHANGES: Mutex<Vec<LocalChange>> = Mutex::new(Vec::new());
+
+    static NEXT_WATCHER: AtomicU64 = AtomicU64::new(0);
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum ChangeKind {
+        Volume,
+        Mute,
+        Default,
+        ActivePort,
+    }
+
+    struct LocalChange {
+        uid: String,
+        kind: ChangeKind,
+        identity: ClientIdentity,
+        made: Instant,
+        // Watchers that already attributed a notification to this change
+        matched_by: Vec<u64>,
+    }
+
+    /// Called by devices right before a request is sent, see `PulseAudioDevice::record_change`
+    pub(crate) fn record_change(uid: &str, kind: ChangeKind, identity: &ClientIdentity) {
+        let mut changes = LOCAL_CHANGES.lock().unwrap();
+        changes.retain(|change| change.made.elapsed() < LOCAL_CHANGE_WINDOW);
+        changes.push(LocalChange {
+            uid: uid.to_string(),
+            kind,
+            identity: identity.clone(),
+            made: Instant::now(),
+            matched_by: Vec::new(),
+        });
+    }
+
+    // Each change accounts for one notification per watcher, oldest first, so a later change by
+    // another client within the window is not attributed to this process as well
+    fn find_origin(watcher: u64, uid: &str, kind: ChangeKind) -> Option<ClientIdentity> {
+        let mut changes = LOCAL_CHANGES.lock().unwrap();
+        changes.retain(|change| change.made.elapsed() < LOCAL_CHANGE_WINDOW);
+        let change = changes.iter_mut()
+            .find(|change| change.uid == uid && change.kind == kind && !change.matched_by.contains(&watcher))?;
+        change.matched_by.push(watcher);
+        Some(change.identity.clone())
+    }
+
+    struct DeviceRecord {
+        uid: String,
+        volume: ChannelVolumes,
+        mute: bool,
+        ports: HashMap<String, PortAvailability>,
+        active_port: Option<String>,
+    }
+
+    impl DeviceRecord {
+        fn from_sink(device: &SinkInfo) -> Option<Self> {
+            Some(DeviceRecord {
+                uid: device.name.as_ref()?.to_string(),
+                volume: device.volume,
+                mute: device.mute,
+                ports: device.ports.iter()
+                    .filter_map(|port| Some((port.name.as_ref()?.to_string(), to_availability(port.available))))
+                    .collect(),
+                active_port: device.active_port.as_ref().and_then(|port| port.name.as_ref().map(|name| name.to_string())),
+            })
+        }
+
+        fn from_source(device: &SourceInfo) -> Option<Self> {
+            Some(DeviceRecord {
+                uid: device.name.as_ref()?.to_string(),
+                volume: device.volume,
+                mute: device.mute,
+                ports: device.ports.iter()
+                    .filter_map(|port| Some((port.name.as_ref()?.to_string(), to_availability(port.available))))
+                    .collect(),
+                active_port: device.active_port.as_ref().and_then(|port| port.name.as_ref().map(|name| name.to_string())),
+            })
+        }
+    }
+
+    // State shared between the PulseAudio callbacks, used to turn raw
+    // "something changed" notifications into typed events
+    struct Watcher {
+        id: u64,
+        sender: Sender<Event>,
+        sinks: HashMap<u32, DeviceRecord>,
+        sources: HashMap<u32, DeviceRecord>,
+        default_sink: Option<String>,
+        default_source: Option<String>,
+        // Cache is filled before the first notification is handled
+        ready: bool,
+    }
+
+    impl Watcher {
+        fn devices(&mut self, device_type: DeviceType) -> &mut HashMap<u32, DeviceRecord> {
+            match device_type {
+                DeviceType::Input => &mut self.sources,
+                _ => &mut self.sinks,
+            }
+        }
+
+        // Notifications handled while filling the cache are not emitted, so they do not use up changes either
+        fn origin(&self, uid: &str, kind: ChangeKind) -> Option<ClientIdentity> {
+            if !self.ready {
+                return None;
+            }
+            find_origin(self.id, uid, kind)
+        }
+
+        fn emit(&self, event: Event) {
+            if self.ready && self.sender.send(event).is_err() {
+                debug_eprintln("Subscription receiver dropped");
+            }
+        }
+
+        fn update_device(&mut self, device_type: DeviceType, index: u32, record: DeviceRecord) {
+            let uid = record.uid.clone();
+            let volume = record.volume;
+            let mute = record.mute;
+            let ports = record.ports.clone();
+            let active_port = record.active_port.clone();
+            let previous = self.devices(device_type).insert(index, record);
+            match previous {
+                None => {
+                    self.emit(Event::DeviceAdded { uid, device_type });
+                },
+                Some(previous) => {
+                    if previous.volume != volume {
+                        self.emit(Event::VolumeChanged { uid: uid.clone(), volume: to_volume(&volume, mute), origin: self.origin(&uid, ChangeKind::Volume) });
+                    }
+                    if previous.mute != mute {
+                        self.emit(Event::MuteChanged { uid: uid.clone(), mute, origin: self.origin(&uid, ChangeKind::Mute) });
+                    }
+                    for (port, availability) in ports {
+                        if previous.ports.get(&port).is_some_and(|previous| *previous != availability) {
+                            self.emit(Event::PortAvailabilityChanged { uid: uid.clone(), port, availability });
+                        }
+                    }
+                    if let Some(port) = active_port && previous.active_port.as_ref() != Some(&port) {
+                        self.emit(Event::ActivePortChanged { origin: self.origin(&uid, ChangeKind::ActivePort), uid, port });
+                    }
+                },
+            }
+        }
+
+        fn remove_device(&mut self, device_type: DeviceType, index: u32) {
+            if let Some(previous) = self.devices(device_type).remove(&index) {
+                self.emit(Event::DeviceRemoved { uid: previous.uid, device_type });
+            }
+        }
+
+        fn update_defaults(&mut self, default_sink: Option<String>, default_source: Option<String>) {
+            if let Some(uid) = default_sink && self.default_sink.as_ref() != Some(&uid) {
+                self.default_sink = Some(uid.clone());
+                self.emit(Event::DefaultDeviceChanged { origin: self.origin(&uid, ChangeKind::Default), uid, device_type: DeviceType::Output });
+            }
+            if let Some(uid) = default_source && self.default_source.as_ref() != Some(&uid) {
+                self.default_source = Some(uid.clone());
+                self.emit(Event::DefaultDeviceChanged { origin: self.origin(&uid, ChangeKind::Default), uid, device_type: DeviceType::Input });
+            }
+        }
+    }
+
+    // Same value `Device::get_vol` reports: the loudest channel on the crate wide curve, 0.0 while muted
+    fn to_volume(volume: &ChannelVolumes, mute: bool) -> f32 {
+        if mute {
+            return 0.0;
+        }
+        VolumeCurve::Cubic.convert(volume.max().0 as f32 / PA_VOLUME_NORM as f32, curve::get_volume_curve())
+    }
+
+    fn refresh_sink(introspector: &Introspector, watcher: &Arc<Mutex<Watcher>>, index: u32) {
+        let watcher = Arc::clone(watcher);
+        introspector.get_sink_info_by_index(index, move |info| {
+            if let ListResult::Item(device) = info && let Some(record) = DeviceRecord::from_sink(device) {
+                watcher.lock().unwrap().update_device(DeviceType::Output, device.index, record);
+            }
+        });
+    }
+
+    fn refresh_source(introspector: &Introspector, watcher: &Arc<Mutex<Watcher>>, index: u32) {
+        let watcher = Arc::clone(watcher);
+        introspector.get_source_info_by_index(index, move |info| {
+            if let ListResult::Item(device) = info && let Some(record) = DeviceRecord::from_source(device) {
+                watcher.lock().unwrap().update_device(DeviceType::Input, device.index, record);
+            }
+        });
+    }
+
+    // Jack detection is reported on the card, the sinks and sources of the card carry the port state
+    fn refresh_card(introspector: &Introspector, watcher: &Arc<Mutex<Watcher>>, card: u32) {
+        let sinks = Arc::clone(watcher);
+        introspector.get_sink_info_list(move |info| {
+            if let ListResult::Item(device) = info && device.card == Some(card) && let Some(record) = DeviceRecord::from_sink(device) {
+                sinks.lock().unwrap().update_device(DeviceType::Output, device.index, record);
+            }
+        });
+        let sources = Arc::clone(watcher);
+        introspector.get_source_info_list(move |info| {
+            if let ListResult::Item(device) = info && device.card == Some(card) && let Some(record) = DeviceRecord::from_source(device) {
+                sources.lock().unwrap().update_device(DeviceType::Input, device.index, record);
+            }
+        });
+    }
+
+    fn refresh_server(introspector: &Introspector, watcher: &Arc<Mutex<Watcher>>) {
+        let watcher = Arc::clone(watcher);
+        introspector.get_server_info(move |info| {
+            let mut watcher = watcher.lock().unwrap();
+            watcher.update_defaults(
+                info.default_sink_name.as_ref().map(|name| name.to_string()),
+                info.default_source_name.as_ref().map(|name| name.to_string()),
+            );
+            watcher.ready = true;
+        });
+    }
+
+    // Fills the cache, the server answers requests in order so `ready` is
+    // set by the server info callback once every device has been recorded
+    fn populate(introspector: &Introspector, watcher: &Arc<Mutex<Watcher>>) {
+        let sinks = Arc::clone(watcher);
+        introspector.get_sink_info_list(move |info| {
+            if let ListResult::Item(device) = info && let Some(record) = DeviceRecord::from_sink(device) {
+                sinks.lock().unwrap().update_device(DeviceType::Output, device.index, record);
+            }
+        });
+        let sources = Arc::clone(watcher);
+        introspector.get_source_info_list(move |info| {
+            if let ListResult::Item(device) = info && let Some(record) = DeviceRecord::from_source(device) {
+                sources.lock().unwrap().update_device(DeviceType::Input, device.index, record);
+            }
+        });
+        refresh_server(introspector, watcher);
+    }
+
+    fn connect(mainloop: &mut Mainloop, options: &ConnectionOptions) -> Result<Context, Error> {
+        let proplist = pulseaudio::to_proplist(&options.identity)?;
+        let mut context = Context::new_with_proplist(mainloop, &options.identity.get_name(), &proplist)
+            .ok_or(Error::DeviceAccessFailed(format!("Failed to create connection context")))?;
+        pulseaudio::connect_context(&mut context, options)?;
+        mainloop.start()
+            .map_err(pulseaudio::to_error)?;
+
+        loop {
+            mainloop.lock();
+            let state = context.get_state();
+            mainloop.unlock();
+            match state {
+                libpulse_binding::context::State::Ready => break,
+                libpulse_binding::context::State::Failed | libpulse_binding::context::State::Terminated => {
+                    mainloop.stop();
+                    return Err(pulseaudio::to_error(context.errno()));
+                }
+                _ => {
+                    thread::sleep(Duration::from_millis(5));
+                }
+            }
+        }
+        Ok(context)
+    }
+
+    /// Starts delivering device, default device, volume, mute and port changes to `sender`
+    ///
+    /// A dedicated connection to the server of the shared session is kept open on a threaded mainloop until the subscription is dropped
+    pub fn subscribe(sender: Sender<Event>) -> Result<Subscription, Error> {
+        let options = session::get_shared_options();
+        let (setup_tx, setup_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || {
+            let mut mainloop = match Mainloop::new() {
+                Some(mainloop) => mainloop,
+                None => {
+                    let _ = setup_tx.send(Err(Error::DeviceAccessFailed(format!("Failed to create mainloop"))));
+                    return;
+                }
+            };
+            let mut context = match connect(&mut mainloop, &options) {
+                Ok(context) => context,
+                Err(error) => {
+                    let _ = setup_tx.send(Err(error));
+                    return;
+                }
+            };
+
+            let watcher = Arc::new(Mutex::new(Watcher {
+                id: NEXT_WATCHER.fetch_add(1, Ordering::Relaxed),
+                sender,
+                sinks: HashMap::new(),
+                sources: HashMap::new(),
+                default_sink: None,
+                default_source: None,
+                ready: false,
+            }));
+
+            mainloop.lock();
+            let introspector = context.introspect();
+            populate(&introspector, &watcher);
+
+            let callback_watcher = Arc::clone(&watcher);
+            context.set_subscribe_callback(Some(Box::new(move |facility, operation, index| {
+                match (facility, operation) {
+                    (Some(Facility::Sink), Some(Operation::Removed)) => {
+                        callback_watcher.lock().unwrap().remove_device(DeviceType::Output, index);
+                    },
+                    (Some(Facility::Sink), Some(_)) => {
+                        refresh_sink(&introspector, &callback_watcher, index);
+                    },
+                    (Some(Facility::Source), Some(Operation::Removed)) => {
+                        callback_watcher.lock().unwrap().remove_device(DeviceType::Input, index);
+                    },
+                    (Some(Facility::Source), Some(_)) => {
+                        refresh_source(&introspector, &callback_watcher, index);
+                    },
+                    (Some(Facility::Card), Some(Operation::Changed)) => {
+                        refresh_card(&introspector, &callback_watcher, index);
+                    },
+                    (Some(Facility::Server), Some(_)) => {
+                        refresh_server(&introspector, &callback_watcher);
+                    },
+                    _ => {},
+                }
+            })));
+            context.subscribe(InterestMaskSet::SINK | InterestMaskSet::SOURCE | InterestMaskSet::CARD | InterestMaskSet::SERVER, |success| {
+                if !success {
+                    debug_eprintln("Failed to subscribe to server events");
+                }
+            });
+            mainloop.unlock();
+
+            let _ = setup_tx.send(Ok(()));
+            // Returns once the subscription is dropped
+            let _ = stop_rx.recv();
+
+            mainloop.lock();
+            context.set_subscribe_callback(None);
+            context.disconnect();
+            mainloop.unlock();
+            mainloop.stop();
+        });
+
+        match setup_rx.recv() {
+            Ok(Ok(())) => {
+                Ok(Subscription::new(move || {
+                    let _ = stop_tx.send(());
+                    let _ = handle.join();
+                }))
+            },
+            Ok(Err(error)) => Err(error),
+            Err(_) => Err(Error::DeviceAccessFailed(format!("Subscription thread exited unexpectedly"))),
+        }
+    }
+
+}
+
+#[cfg(not(target_os="linux"))]
+// #[cfg(target_os="linux")]
+
+mod subscribe {
+
+    use std::sync::mpsc::Sender;
+    use crate::{error::Error, event::{Event, Subscription}};
+
+    pub fn subscribe(sender: Sender<Event>) -> Result<Subscription, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
+}
+
+pub(crate) use subscribe::*;
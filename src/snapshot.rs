@@ -0,0 +1,217 @@
+use crate::{DeviceType, device::{ChannelVolume, Device}, error::Error};
+
+/// Mixer state captured by `cpvc::snapshot`, kept in a file through the `serde` feature
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    pub devices: Vec<DeviceSnapshot>,
+    pub default_output: Option<String>,
+    pub default_input: Option<String>,
+    /// Application streams, empty on backends without per-application volumes
+    pub streams: Vec<StreamSnapshot>,
+}
+
+/// Volumes are on the device's native curve so they come back exactly whatever curve is picked later
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceSnapshot {
+    pub uid: String,
+    pub device_type: DeviceType,
+    pub volume: f32,
+    /// Empty when the backend has no per-channel volumes, `volume` is restored instead
+    pub channels: Vec<ChannelVolume>,
+    pub mute: bool,
+    pub active_port: Option<String>,
+}
+
+/// Streams are matched by application name, stream name and binary since their indices do not survive a restart
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamSnapshot {
+    pub application_name: Option<String>,
+    pub name: Option<String>,
+    pub binary: Option<String>,
+    pub device_uid: Option<String>,
+    pub volume: f32,
+    pub mute: bool,
+}
+
+/// What `cpvc::restore` could not put back, everything else was applied
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RestoreReport {
+    /// UIDs of devices (including former defaults) that are gone
+    pub missing_devices: Vec<String>,
+    pub missing_streams: Vec<StreamSnapshot>,
+    /// Device UID or stream description with the error applying it ran into
+    pub failed: Vec<(String, Error)>,
+}
+
+impl RestoreReport {
+    pub fn is_complete(&self) -> bool {
+        self.missing_devices.is_empty() && self.missing_streams.is_empty() && self.failed.is_empty()
+    }
+}
+
+// Optional parts of the state are left out on backends that do not have them
+fn supported<T>(result: Result<T, Error>, fallback: T) -> Result<T, Error> {
+    match result {
+        Err(Error::PlatformUnsupported) => Ok(fallback),
+        result => result,
+    }
+}
+
+fn to_native(mut device: Device) -> Result<Device, Error> {
+    device.set_curve(Some(device.get_native_curve()?))?;
+    Ok(device)
+}
+
+pub(crate) fn capture_device(uid: String) -> Result<DeviceSnapshot, Error> {
+    let device = to_native(Device::from_uid(uid)?)?;
+    Ok(DeviceSnapshot {
+        uid: device.get_uid()?,
+        device_type: device.get_device_type()?,
+        volume: device.get_vol()?,
+        channels: supported(device.get_channel_vols(), Vec::new())?,
+        mute: device.get_mute()?,
+        active_port: supported(device.get_active_port(), None)?.map(|port| port.name),
+    })
+}
+
+fn restore_device(device: &Device, snapshot: &DeviceSnapshot) -> Result<(), Error> {
+    // Switching ports makes some servers load the volume of the new port, so the port goes first
+    if let Some(port) = snapshot.active_port.as_ref() && supported(device.get_active_port(), None)?.is_none_or(|active| active.name != *port) {
+        device.set_active_port(port.clone())?;
+    }
+    let boosted = snapshot.volume > 1.0 || snapshot.channels.iter().any(|channel| channel.volume > 1.0);
+    let allow_boost = boosted && device.supports_boost()? && !device.is_boost_allowed()?;
+    if allow_boost {
+        device.set_boost_allowed(true)?;
+    }
+    let result = restore_volume(device, snapshot);
+    if allow_boost {
+        device.set_boost_allowed(false)?;
+    }
+    result?;
+    device.set_mute(snapshot.mute)
+}
+
+fn restore_volume(device: &Device, snapshot: &DeviceSnapshot) -> Result<(), Error> {
+    let channels = supported(device.get_channel_vols(), Vec::new())?;
+    // A device whose channel layout changed only gets its overall volume back
+    if snapshot.channels.is_empty() || channels.len() != snapshot.channels.len() {
+        return device.set_vol(snapshot.volume);
+    }
+    for (index, channel) in snapshot.channels.iter().enumerate() {
+        device.set_channel_vol(index, channel.volume)?;
+    }
+    Ok(())
+}
+
+fn restore_default(uid: &str, report: &mut RestoreReport) {
+    match Device::from_uid(uid.to_string()).and_then(|device| device.make_default(false)) {
+        Ok(()) => {},
+        Err(Error::DeviceNotFound) => {
+            if !report.missing_devices.iter().any(|missing| missing == uid) {
+                report.missing_devices.push(uid.to_string());
+            }
+        },
+        Err(error) => report.failed.push((uid.to_string(), error)),
+    }
+}
+
+pub(crate) fn restore(snapshot: &Snapshot) -> Result<RestoreReport, Error> {
+    let mut report = RestoreReport::default();
+    for device_snapshot in snapshot.devices.iter() {
+        match Device::from_uid(device_snapshot.uid.clone()).and_then(to_native) {
+            Ok(device) => {
+                if let Err(error) = restore_device(&device, device_snapshot) {
+                    report.failed.push((device_snapshot.uid.clone(), error));
+                }
+            },
+            Err(Error::DeviceNotFound) => report.missing_devices.push(device_snapshot.uid.clone()),
+            Err(error) => report.failed.push((device_snapshot.uid.clone(), error)),
+        }
+    }
+    for uid in [snapshot.default_output.as_ref(), snapshot.default_input.as_ref()].into_iter().flatten() {
+        restore_default(uid, &mut report);
+    }
+    if !snapshot.streams.is_empty() {
+        streams::restore(&snapshot.streams, &mut report)?;
+    }
+    Ok(report)
+}
+
+pub(crate) use streams::capture as capture_streams;
+
+#[cfg(all(target_os = "linux", not(feature = "mock")))]
+mod streams {
+    use crate::{device::Device, error::Error, linux::{self, Backend}, pulseaudio::stream::{self, ApplicationStream}};
+    use super::{RestoreReport, StreamSnapshot};
+
+    impl StreamSnapshot {
+        fn matches(&self, stream: &ApplicationStream) -> bool {
+            self.application_name == stream.get_application_name() && self.name == stream.get_name() && self.binary == stream.get_binary()
+        }
+
+        fn describe(&self) -> String {
+            self.application_name.clone().or(self.name.clone()).unwrap_or_else(|| format!("Unnamed stream"))
+        }
+    }
+
+    // Only PulseAudio exposes application streams
+    pub(crate) fn capture() -> Result<Vec<StreamSnapshot>, Error> {
+        if linux::get_backend() != Backend::PulseAudio {
+            return Ok(Vec::new());
+        }
+        stream::get_application_streams()?.into_iter()
+            .map(|stream| Ok(StreamSnapshot {
+                application_name: stream.get_application_name(),
+                name: stream.get_name(),
+                binary: stream.get_binary(),
+                device_uid: stream.get_sink_uid(),
+                volume: stream.get_vol()?,
+                mute: stream.get_mute()?,
+            }))
+            .collect()
+    }
+
+    pub(crate) fn restore(snapshots: &[StreamSnapshot], report: &mut RestoreReport) -> Result<(), Error> {
+        let mut streams = stream::get_application_streams()?;
+        for snapshot in snapshots {
+            // Each stream is used once, so two streams of the same application map to two streams again
+            let Some(position) = streams.iter().position(|stream| snapshot.matches(stream)) else {
+                report.missing_streams.push(snapshot.clone());
+                continue;
+            };
+            let mut stream = streams.remove(position);
+            if let Err(error) = restore_stream(&mut stream, snapshot) {
+                report.failed.push((snapshot.describe(), error));
+            }
+        }
+        Ok(())
+    }
+
+    fn restore_stream(stream: &mut ApplicationStream, snapshot: &StreamSnapshot) -> Result<(), Error> {
+        if let Some(uid) = snapshot.device_uid.as_ref() && stream.get_sink_uid().as_ref() != Some(uid) {
+            stream.move_to(&Device::from_uid(uid.clone())?)?;
+        }
+        stream.set_vol(snapshot.volume)?;
+        stream.set_mute(snapshot.mute)
+    }
+}
+
+#[cfg(not(all(target_os = "linux", not(feature = "mock"))))]
+mod streams {
+    use crate::error::Error;
+    use super::{RestoreReport, StreamSnapshot};
+
+    pub(crate) fn capture() -> Result<Vec<StreamSnapshot>, Error> {
+        Ok(Vec::new())
+    }
+
+    pub(crate) fn restore(snapshots: &[StreamSnapshot], report: &mut RestoreReport) -> Result<(), Error> {
+        report.missing_streams.extend(snapshots.iter().cloned());
+        Ok(())
+    }
+}
@@ -84,6 +84,10 @@ pub mod wasapi {
         Ok(get_device_identifiers()?.into_iter().map(|(_pwstr, name)| name).collect())
     }
 
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Ok(get_device_identifiers()?.into_iter().map(|(uid, _name)| uid).collect())
+    }
+
     pub fn get_vol() -> Result<f32, Error> {
         let default_device = get_default_output_device()?;
         Ok(default_device.get_vol()?)
@@ -137,6 +141,10 @@ pub mod wasapi {
         Err(Error::PlatformUnsupported)
     }
 
+    pub fn get_sound_device_uids() -> Result<Vec<String>, Error> {
+        Err(Error::PlatformUnsupported)
+    }
+
     pub fn get_vol() -> Result<f32, Error> {
         Err(Error::PlatformUnsupported)
     }